@@ -0,0 +1,305 @@
+use crate::{
+    changelog::Category, command::Git, config::LumenConfig, conventional_commit,
+    git_branch::GitBranch, git_commit::GitCommit, git_range::GitRange, git_repo::GitRepo,
+    git_staged::GitStaged, pr_template::PrTemplate, prompt_template::PromptTemplate, CommitFormat,
+};
+
+#[derive(Clone, Debug)]
+pub struct AIPrompt {
+    pub system_prompt: String,
+    pub user_prompt: String,
+    /// Earlier turns of a conversation, sent between the system prompt and
+    /// the user prompt. Empty outside of `chat`.
+    pub history: Vec<ChatMessage>,
+}
+
+/// Who a message in a conversation is from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    /// The role as the OpenAI, Ollama and Claude APIs name it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl AIPrompt {
+    pub fn build_explain_prompt(git: &Git, config: &LumenConfig) -> Self {
+        match git {
+            Git::Commit(commit) => {
+                let template = template("explain", config);
+                template.render(&[
+                    ("hash", commit.full_hash.clone()),
+                    (
+                        "author",
+                        format!("{} <{}>", commit.author_name, commit.author_email),
+                    ),
+                    ("date", commit.date.clone()),
+                    ("message", commit.message.clone()),
+                    ("branch", branch(&template)),
+                    ("files", commit.files.join(", ")),
+                    ("diff", commit.diff.clone()),
+                    ("omitted", omitted_note(&commit.omitted_files)),
+                ])
+            }
+            Git::Staged(staged) => {
+                let template = template("suggest", config);
+                template.render(&[
+                    ("branch", branch(&template)),
+                    ("files", staged.files.join(", ")),
+                    ("diff", staged.diff.clone()),
+                    ("omitted", omitted_note(&staged.omitted_files)),
+                ])
+            }
+            Git::Range(range) => {
+                let template = template("range", config);
+                template.render(&[
+                    ("range", range.range.clone()),
+                    ("commits", commit_messages(range)),
+                    ("branch", branch(&template)),
+                    ("files", range.files.join(", ")),
+                    ("diff", range.diff.clone()),
+                    ("omitted", omitted_note(&range.omitted_files)),
+                ])
+            }
+        }
+    }
+
+    pub fn build_commit_prompt(
+        staged: &GitStaged,
+        format: CommitFormat,
+        config: &LumenConfig,
+    ) -> Self {
+        let format_instructions = match format {
+            CommitFormat::Plain => String::from(
+                "Use this format:\n\
+                 - a subject line of at most 72 characters, without a trailing period\n\
+                 - a blank line\n\
+                 - a body wrapped at 72 characters explaining what changed and why",
+            ),
+            CommitFormat::Conventional => format!(
+                "Follow the Conventional Commits 1.0.0 specification:\n\
+                 - a header `type(scope)!: description` of at most 72 characters, where type is one of {}, \
+                 the scope is optional and `!` is only added for breaking changes\n\
+                 - a blank line\n\
+                 - a body wrapped at 72 characters explaining what changed and why\n\
+                 - if the change breaks backwards compatibility, a final paragraph starting with \
+                 `BREAKING CHANGE: ` describing what breaks and how to migrate",
+                conventional_commit::TYPES.join(", ")
+            ),
+        };
+
+        let template = template("commit", config);
+        template.render(&[
+            ("format", format_instructions),
+            ("branch", branch(&template)),
+            ("files", staged.files.join(", ")),
+            ("diff", staged.diff.clone()),
+            ("omitted", omitted_note(&staged.omitted_files)),
+        ])
+    }
+
+    pub fn build_pr_prompt(
+        branch: &GitBranch,
+        range: &GitRange,
+        pr_template: &PrTemplate,
+        config: &LumenConfig,
+    ) -> Self {
+        template("pr", config).render(&[
+            ("template", pr_template.content.trim().to_string()),
+            ("branch", branch.name.clone()),
+            ("base", branch.base.clone()),
+            ("commits", commit_messages(range)),
+            ("files", range.files.join(", ")),
+            ("diff", range.diff.clone()),
+            ("omitted", omitted_note(&range.omitted_files)),
+        ])
+    }
+
+    /// Asks the provider to sort commits that do not follow Conventional
+    /// Commits into changelog categories.
+    pub fn build_changelog_prompt(commits: &[&GitCommit], config: &LumenConfig) -> Self {
+        let categories = Category::ALL
+            .iter()
+            .map(|category| category.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let commit_list = commits
+            .iter()
+            .enumerate()
+            .map(|(i, commit)| format!("{}. {}", i + 1, commit.message.trim()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        template("changelog", config)
+            .render(&[("categories", categories), ("commits", commit_list)])
+    }
+
+    /// The map step for diffs too large for the context window: summarizes
+    /// one chunk of the diff.
+    pub fn build_chunk_prompt(
+        chunk: &str,
+        part: usize,
+        parts: usize,
+        config: &LumenConfig,
+    ) -> Self {
+        template("chunk", config).render(&[
+            ("part", part.to_string()),
+            ("parts", parts.to_string()),
+            ("diff", chunk.to_string()),
+        ])
+    }
+
+    /// The reduce step for diffs too large for the context window: merges
+    /// the summaries of consecutive chunks into one.
+    pub fn build_merge_prompt(summaries: &[String], config: &LumenConfig) -> Self {
+        template("merge", config).render(&[("summaries", summaries.join("\n\n"))])
+    }
+
+    /// The next question in a conversation about `git`, after the earlier
+    /// `turns`. The changes are sent along with the first question, so that
+    /// the later ones only add to the history.
+    pub fn build_chat_prompt(
+        git: &Git,
+        turns: &[ChatMessage],
+        question: &str,
+        config: &LumenConfig,
+    ) -> Self {
+        let (changes, files, diff, omitted) = match git {
+            Git::Commit(commit) => (
+                format!(
+                    "Commit {} by {} <{}> on {}:\n{}",
+                    commit.full_hash,
+                    commit.author_name,
+                    commit.author_email,
+                    commit.date,
+                    commit.message
+                ),
+                &commit.files,
+                &commit.diff,
+                &commit.omitted_files,
+            ),
+            Git::Range(range) => (
+                format!("Commits in {}:\n{}", range.range, commit_messages(range)),
+                &range.files,
+                &range.diff,
+                &range.omitted_files,
+            ),
+            Git::Staged(staged) => (
+                "Staged changes that are not committed yet".to_string(),
+                &staged.files,
+                &staged.diff,
+                &staged.omitted_files,
+            ),
+        };
+
+        let template = template("chat", config);
+        let first_question = turns.first().map_or(question, |turn| turn.content.as_str());
+        let opening = template.render(&[
+            ("changes", changes),
+            ("branch", branch(&template)),
+            ("files", files.join(", ")),
+            ("diff", diff.clone()),
+            ("omitted", omitted_note(omitted)),
+            ("question", first_question.to_string()),
+        ]);
+
+        if turns.is_empty() {
+            return opening;
+        }
+
+        let mut history = turns.to_vec();
+        history[0].content = opening.user_prompt;
+        AIPrompt {
+            system_prompt: opening.system_prompt,
+            user_prompt: question.to_string(),
+            history,
+        }
+    }
+
+    /// Asks for the answer as a JSON object following
+    /// [`Summary::schema`](crate::summary::Summary::schema), for providers
+    /// without structured output.
+    pub fn with_json_output(&self) -> Self {
+        AIPrompt {
+            system_prompt: self.system_prompt.clone(),
+            user_prompt: format!(
+                "{}\n\nRespond with a JSON object only, without code fences or commentary, \
+                 with these fields:\n\
+                 - title: a one-line title of at most 72 characters\n\
+                 - summary: one paragraph describing what changed and why\n\
+                 - changes: a list of the individual changes, one sentence each",
+                self.user_prompt
+            ),
+            history: self.history.clone(),
+        }
+    }
+
+    /// Extends a prompt after the provider returned a message that failed
+    /// validation, so that the next attempt can correct it.
+    pub fn with_correction(&self, previous: &str, problem: &str) -> Self {
+        AIPrompt {
+            system_prompt: self.system_prompt.clone(),
+            user_prompt: format!(
+                "{}\n\nA previous answer was rejected because {problem}:\n{previous}\n\n\
+                 Please answer again, following the format exactly.",
+                self.user_prompt
+            ),
+            history: self.history.clone(),
+        }
+    }
+}
+
+/// A built-in template, as overridden in the config.
+fn template(name: &str, config: &LumenConfig) -> PromptTemplate {
+    PromptTemplate::get(name, config).expect("the template is built in")
+}
+
+/// The current branch, looked up only for templates that use it. Empty when
+/// HEAD is detached.
+fn branch(template: &PromptTemplate) -> String {
+    if !template.uses("branch") {
+        return String::new();
+    }
+
+    GitRepo::open()
+        .ok()
+        .and_then(|repo| GitBranch::current_name(&repo).ok().flatten())
+        .unwrap_or_default()
+}
+
+/// Tells the provider which files were filtered out of the diff, so that it
+/// does not describe the change as if they were untouched.
+fn omitted_note(files: &[String]) -> String {
+    if files.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        "\n\nThese files also changed but were left out of the diff: {}",
+        files.join(", ")
+    )
+}
+
+fn commit_messages(range: &GitRange) -> String {
+    range
+        .commits
+        .iter()
+        .map(|commit| format!("commit {}\n{}", commit.full_hash, commit.message))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::LumenConfig;
+
+const CACHE_DIR: &str = "lumen";
+const ENTRY_EXTENSION: &str = "json";
+
+#[derive(Debug, Clone)]
+pub enum CacheError {
+    NoCacheDir,
+    IoError(PathBuf, String),
+}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheError::NoCacheDir => write!(
+                f,
+                "Could not find a cache directory (neither XDG_CACHE_HOME nor HOME is set)"
+            ),
+            CacheError::IoError(path, err) => {
+                write!(f, "Could not access cache '{}': {err}", path.display())
+            }
+        }
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> CacheError + '_ {
+    move |err| CacheError::IoError(path.to_path_buf(), err.to_string())
+}
+
+/// What a response is generated for: the subject (a commit hash, or the
+/// hash of a diff) and the prompt template. Responses are stored under the
+/// key of the provider and model that gave them, which with fallbacks is
+/// only known once one has answered.
+pub struct CacheKey {
+    subject: String,
+    template: String,
+}
+
+impl CacheKey {
+    pub fn new(subject: &str, template: &str) -> Self {
+        CacheKey {
+            subject: subject.to_string(),
+            template: hash(template),
+        }
+    }
+
+    /// The key of the response by `provider` and `model`.
+    pub fn by(&self, provider: &str, model: &str) -> String {
+        hash(&[self.subject.as_str(), provider, model, &self.template].join("\n"))
+    }
+}
+
+/// A cached response, along with what it was generated by and for.
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub provider: String,
+    pub model: String,
+    pub commit: Option<String>,
+    pub response: String,
+}
+
+pub struct CacheStats {
+    pub entries: usize,
+    pub expired: usize,
+    pub size: u64,
+    pub oldest: Option<Duration>,
+}
+
+/// Provider responses stored as one file per key under
+/// `$XDG_CACHE_HOME/lumen`. Entries older than the TTL are ignored, and the
+/// oldest entries are removed once the cache grows beyond its size limit.
+pub struct ResponseCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+    pub max_size: u64,
+}
+
+/// A file in the cache directory.
+struct StoredEntry {
+    path: PathBuf,
+    size: u64,
+    age: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(config: &LumenConfig) -> Result<Self, CacheError> {
+        let cache_home = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .ok_or(CacheError::NoCacheDir)?;
+
+        Ok(ResponseCache {
+            dir: cache_home.join(CACHE_DIR),
+            ttl: Duration::from_secs(config.cache_ttl_days.value.saturating_mul(24 * 60 * 60)),
+            max_size: config.cache_max_size_mb.value.saturating_mul(1024 * 1024),
+        })
+    }
+
+    /// The entry stored under `key`, unless it expired. Unreadable entries
+    /// count as missing.
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+        if age > self.ttl {
+            return None;
+        }
+
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// Stores `entry` under `key` and prunes the cache.
+    pub fn put(&self, key: &str, entry: &CacheEntry) -> Result<(), CacheError> {
+        fs::create_dir_all(&self.dir).map_err(io_error(&self.dir))?;
+
+        let path = self.path(key);
+        let content = serde_json::to_string(entry)
+            .map_err(|err| CacheError::IoError(path.clone(), err.to_string()))?;
+        fs::write(&path, content).map_err(io_error(&path))?;
+
+        self.prune()
+    }
+
+    /// Removes expired entries, then the oldest ones until the cache fits in
+    /// its size limit.
+    pub fn prune(&self) -> Result<(), CacheError> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.age));
+
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+        for entry in entries {
+            if entry.age <= self.ttl && size <= self.max_size {
+                break;
+            }
+
+            fs::remove_file(&entry.path).map_err(io_error(&entry.path))?;
+            size -= entry.size;
+        }
+
+        Ok(())
+    }
+
+    /// Removes every entry, returning how many there were.
+    pub fn clear(&self) -> Result<usize, CacheError> {
+        let entries = self.entries()?;
+        for entry in &entries {
+            fs::remove_file(&entry.path).map_err(io_error(&entry.path))?;
+        }
+
+        Ok(entries.len())
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, CacheError> {
+        let entries = self.entries()?;
+
+        Ok(CacheStats {
+            entries: entries.len(),
+            expired: entries.iter().filter(|entry| entry.age > self.ttl).count(),
+            size: entries.iter().map(|entry| entry.size).sum(),
+            oldest: entries.iter().map(|entry| entry.age).max(),
+        })
+    }
+
+    /// The most recent unexpired summary of each commit by one of the
+    /// `answerers` (provider and model), by commit hash, whatever prompt it
+    /// was generated with. Summaries by earlier answerers win.
+    pub fn commit_summaries(
+        &self,
+        answerers: &[(&str, &str)],
+    ) -> Result<HashMap<String, String>, CacheError> {
+        let mut entries = self.entries()?;
+        entries.retain(|entry| entry.age <= self.ttl);
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.age));
+
+        let cached: Vec<CacheEntry> = entries
+            .iter()
+            .filter_map(|entry| {
+                let content = fs::read_to_string(&entry.path).ok()?;
+                serde_json::from_str(&content).ok()
+            })
+            .collect();
+
+        // Entries are visited oldest first and answerers last first, so
+        // newer summaries by earlier answerers win
+        let mut summaries = HashMap::new();
+        for (provider, model) in answerers.iter().rev() {
+            for entry in &cached {
+                if entry.provider != *provider || entry.model != *model {
+                    continue;
+                }
+                if let Some(commit) = &entry.commit {
+                    summaries.insert(commit.clone(), entry.response.clone());
+                }
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension(ENTRY_EXTENSION)
+    }
+
+    fn entries(&self) -> Result<Vec<StoredEntry>, CacheError> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(io_error(&self.dir)(err)),
+        };
+
+        let mut entries = Vec::new();
+        for entry in dir {
+            let path = entry.map_err(io_error(&self.dir))?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+
+            let metadata = fs::metadata(&path).map_err(io_error(&path))?;
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .unwrap_or_default();
+            entries.push(StoredEntry {
+                path,
+                size: metadata.len(),
+                age,
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+/// The SHA-256 of `text` in hex.
+pub fn hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn cache(dir: &tempfile::TempDir) -> ResponseCache {
+        ResponseCache {
+            dir: dir.path().join(CACHE_DIR),
+            ttl: DAY,
+            max_size: 1024 * 1024,
+        }
+    }
+
+    fn entry(commit: &str, provider: &str, response: &str) -> CacheEntry {
+        CacheEntry {
+            provider: provider.to_string(),
+            model: "model".to_string(),
+            commit: Some(commit.to_string()),
+            response: response.to_string(),
+        }
+    }
+
+    /// Makes the entry under `key` look `age` old.
+    fn age(cache: &ResponseCache, key: &str, age: Duration) {
+        let file = fs::File::options()
+            .write(true)
+            .open(cache.path(key))
+            .unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn expired_entries_are_missed_and_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(&dir);
+        cache.put("fresh", &entry("a", "ollama", "Fresh")).unwrap();
+        cache.put("stale", &entry("b", "ollama", "Stale")).unwrap();
+        age(&cache, "stale", 2 * DAY);
+
+        assert_eq!(cache.get("fresh").unwrap().response, "Fresh");
+        assert!(cache.get("stale").is_none());
+        assert_eq!(cache.stats().unwrap().expired, 1);
+
+        cache.prune().unwrap();
+        assert_eq!(cache.stats().unwrap().entries, 1);
+        assert!(!cache.path("stale").exists());
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_beyond_the_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = cache(&dir);
+        for (i, key) in ["oldest", "older", "newest"].into_iter().enumerate() {
+            cache.put(key, &entry(key, "ollama", "Summary")).unwrap();
+            age(&cache, key, Duration::from_secs(60 * (3 - i as u64)));
+        }
+
+        let size = cache.stats().unwrap().size;
+        cache.max_size = size - 1;
+        cache.prune().unwrap();
+
+        assert!(!cache.path("oldest").exists());
+        assert!(cache.path("older").exists());
+        assert!(cache.path("newest").exists());
+    }
+
+    #[test]
+    fn keys_change_with_the_diff_provider_and_model() {
+        let key = |diff: &str| CacheKey::new(&hash(diff), "Summarize {diff}");
+        let diff = "+fn main() {}\n";
+
+        assert_eq!(key(diff).by("groq", "llama"), key(diff).by("groq", "llama"));
+        assert_ne!(
+            key(diff).by("groq", "llama"),
+            key("+fn main() { run() }\n").by("groq", "llama")
+        );
+        assert_ne!(
+            key(diff).by("groq", "llama"),
+            key(diff).by("ollama", "llama")
+        );
+        assert_ne!(
+            key(diff).by("groq", "llama"),
+            key(diff).by("groq", "mixtral")
+        );
+    }
+
+    #[test]
+    fn summaries_by_earlier_answerers_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(&dir);
+        cache.put("1", &entry("a", "groq", "By Groq")).unwrap();
+        cache.put("2", &entry("a", "ollama", "By Ollama")).unwrap();
+        cache
+            .put("3", &entry("b", "ollama", "Only Ollama"))
+            .unwrap();
+        cache.put("4", &entry("c", "claude", "Elsewhere")).unwrap();
+
+        let summaries = cache
+            .commit_summaries(&[("groq", "model"), ("ollama", "model")])
+            .unwrap();
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries["a"], "By Groq");
+        assert_eq!(summaries["b"], "Only Ollama");
+    }
+
+    #[test]
+    fn large_limits_do_not_overflow() {
+        let mut config = LumenConfig::defaults();
+        config.cache_ttl_days.value = u64::MAX;
+        config.cache_max_size_mb.value = u64::MAX;
+
+        if let Ok(cache) = ResponseCache::new(&config) {
+            assert_eq!(cache.ttl, Duration::from_secs(u64::MAX));
+            assert_eq!(cache.max_size, u64::MAX);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::conventional_commit::ConventionalCommit;
+use crate::git_commit::GitCommit;
+
+const HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).
+";
+
+/// The groups a changelog section is divided into, in the order they are
+/// rendered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Category {
+    Breaking,
+    Added,
+    Fixed,
+    Changed,
+    Internal,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Breaking,
+        Category::Added,
+        Category::Fixed,
+        Category::Changed,
+        Category::Internal,
+    ];
+
+    /// The name the provider is asked to answer with.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Breaking => "breaking",
+            Category::Added => "added",
+            Category::Fixed => "fixed",
+            Category::Changed => "changed",
+            Category::Internal => "internal",
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Category::Breaking => "Breaking Changes",
+            Category::Added => "Added",
+            Category::Fixed => "Fixed",
+            Category::Changed => "Changed",
+            Category::Internal => "Internal",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == name.trim().to_lowercase())
+    }
+
+    fn from_conventional(commit: &ConventionalCommit) -> Self {
+        if commit.breaking {
+            return Category::Breaking;
+        }
+
+        match commit.commit_type.as_str() {
+            "feat" => Category::Added,
+            "fix" => Category::Fixed,
+            "docs" | "style" | "refactor" | "test" | "build" | "ci" | "chore" => Category::Internal,
+            _ => Category::Changed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangelogEntry {
+    pub category: Category,
+    pub scope: Option<String>,
+    pub description: String,
+    pub hash: String,
+}
+
+impl ChangelogEntry {
+    /// Classifies a commit by its Conventional Commit header. Returns `None`
+    /// for commits that do not follow the specification.
+    pub fn from_conventional(commit: &GitCommit) -> Option<Self> {
+        let conventional = ConventionalCommit::parse(&commit.message).ok()?;
+
+        Some(ChangelogEntry {
+            category: Category::from_conventional(&conventional),
+            scope: conventional.scope,
+            description: conventional.description,
+            hash: commit.full_hash.clone(),
+        })
+    }
+
+    pub fn new(commit: &GitCommit, category: Category) -> Self {
+        ChangelogEntry {
+            category,
+            scope: None,
+            description: commit
+                .message
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            hash: commit.full_hash.clone(),
+        }
+    }
+}
+
+/// One version section of a Keep a Changelog file.
+#[derive(Clone, Debug)]
+pub struct Changelog {
+    pub version: Option<String>,
+    pub date: String,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+impl Changelog {
+    /// Renders the section as Markdown, e.g. `## [1.3.0] - 2024-05-01`
+    /// followed by one list per category. Sections without a version are
+    /// rendered as `## [Unreleased]`.
+    pub fn render(&self) -> String {
+        let mut markdown = match &self.version {
+            Some(version) => format!("## [{version}] - {}\n", self.date),
+            None => String::from("## [Unreleased]\n"),
+        };
+
+        for category in Category::ALL {
+            let entries: Vec<&ChangelogEntry> = self
+                .entries
+                .iter()
+                .filter(|entry| entry.category == category)
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+
+            markdown.push_str(&format!("\n### {}\n\n", category.heading()));
+            for entry in entries {
+                let scope = match &entry.scope {
+                    Some(scope) => format!("**{scope}:** "),
+                    None => String::new(),
+                };
+                let hash = entry.hash.get(..7).unwrap_or(&entry.hash);
+                markdown.push_str(&format!("- {scope}{} (`{hash}`)\n", entry.description));
+            }
+        }
+
+        markdown
+    }
+}
+
+/// The version a release tag names, e.g. `1.3.0` for `v1.3.0`.
+pub fn tag_version(tag: &str) -> String {
+    tag.strip_prefix('v')
+        .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(tag)
+        .to_string()
+}
+
+/// Parses `<number>: <category>` lines into one category per commit, in the
+/// order the commits were numbered. Commits the response does not mention
+/// are left as `None`.
+pub fn parse_classification(response: &str, count: usize) -> Vec<Option<Category>> {
+    let mut categories = vec![None; count];
+
+    for line in response.lines() {
+        let Some((number, name)) = line.split_once(':') else {
+            continue;
+        };
+        let number = number.trim().trim_start_matches(['-', '*', ' ']);
+        let (Ok(number), Some(category)) = (number.parse::<usize>(), Category::from_name(name))
+        else {
+            continue;
+        };
+
+        if let Some(slot) = number.checked_sub(1).and_then(|i| categories.get_mut(i)) {
+            *slot = Some(category);
+        }
+    }
+
+    categories
+}
+
+/// Inserts `section` above the newest version in the changelog at `path`,
+/// creating the file with the standard Keep a Changelog header if it does
+/// not exist yet.
+pub fn prepend(path: &Path, section: &str) -> Result<(), io::Error> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => HEADER.to_string(),
+        Err(err) => return Err(err),
+    };
+
+    fs::write(path, insert_section(&content, section))
+}
+
+/// `content` with `section` inserted above the newest version. An
+/// `## [Unreleased]` section stays at the top, above the inserted one.
+fn insert_section(content: &str, section: &str) -> String {
+    let mut headings = section_headings(content).into_iter().peekable();
+    headings.next_if(|(_, heading)| heading.eq_ignore_ascii_case("## [Unreleased]"));
+    let position = headings
+        .next()
+        .map_or(content.len(), |(position, _)| position);
+
+    let (before, after) = content.split_at(position);
+    let mut updated = before.trim_end().to_string();
+    if !updated.is_empty() {
+        updated.push_str("\n\n");
+    }
+    updated.push_str(section.trim_end());
+    updated.push('\n');
+    if !after.is_empty() {
+        updated.push('\n');
+        updated.push_str(after);
+    }
+
+    updated
+}
+
+/// The `## ` headings in `content` and where they start, leaving out lines
+/// in fenced code blocks.
+fn section_headings(content: &str) -> Vec<(usize, &str)> {
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut position = 0;
+
+    for line in content.split_inclusive('\n') {
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| line.trim_start().starts_with(marker));
+
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            (None, None) if line.starts_with("## ") => headings.push((position, line.trim_end())),
+            _ => {}
+        }
+        position += line.len();
+    }
+
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION: &str = "## [1.1.0] - 2024-06-01\n\n### Added\n\n- New thing (`abc1234`)\n";
+
+    #[test]
+    fn inserts_above_the_newest_version() {
+        let content = format!("{HEADER}\n## [1.0.0] - 2024-05-01\n\n- First\n");
+
+        assert_eq!(
+            insert_section(&content, SECTION),
+            format!("{HEADER}\n{SECTION}\n## [1.0.0] - 2024-05-01\n\n- First\n")
+        );
+    }
+
+    #[test]
+    fn inserts_below_the_unreleased_section() {
+        let content = "# Changelog\n\n## [Unreleased]\n\n- Pending\n\n## [1.0.0] - 2024-05-01\n";
+
+        assert_eq!(
+            insert_section(content, SECTION),
+            format!("# Changelog\n\n## [Unreleased]\n\n- Pending\n\n{SECTION}\n## [1.0.0] - 2024-05-01\n")
+        );
+    }
+
+    #[test]
+    fn skips_headings_in_code_fences() {
+        let content =
+            "# Changelog\n\n```md\n## [0.0.1] - example\n```\n\n## [1.0.0] - 2024-05-01\n";
+
+        assert_eq!(
+            insert_section(content, SECTION),
+            format!("# Changelog\n\n```md\n## [0.0.1] - example\n```\n\n{SECTION}\n## [1.0.0] - 2024-05-01\n")
+        );
+    }
+
+    #[test]
+    fn appends_to_a_changelog_without_versions() {
+        assert_eq!(
+            insert_section(HEADER, SECTION),
+            format!("{HEADER}\n{SECTION}")
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use super::{Git, LumenCommand};
+use crate::ai_prompt::AIPrompt;
+use crate::cache::{self, CacheEntry, CacheKey, ResponseCache};
+use crate::config::{self, LumenConfig};
+use crate::error::LumenError;
+use crate::OutputFormat;
+
+impl LumenCommand {
+    pub fn clear_cache(config: &LumenConfig) -> Result<(), LumenError> {
+        let cache = ResponseCache::new(config)?;
+        let removed = cache.clear()?;
+        println!(
+            "Removed {removed} cached responses from {}",
+            cache.dir.display()
+        );
+        Ok(())
+    }
+
+    pub fn cache_stats(config: &LumenConfig) -> Result<(), LumenError> {
+        let cache = ResponseCache::new(config)?;
+        let stats = cache.stats()?;
+
+        println!("Directory: {}", cache.dir.display());
+        println!("Entries:   {} ({} expired)", stats.entries, stats.expired);
+        println!(
+            "Size:      {} of {}",
+            format_size(stats.size),
+            format_size(cache.max_size)
+        );
+        if let Some(oldest) = stats.oldest {
+            println!("Oldest:    {}", format_age(oldest));
+        }
+        Ok(())
+    }
+
+    /// The cache key of the summary of `git`. Commits are keyed by their
+    /// hash and staged changes by their diff, along with the prompt (without
+    /// the diff) they would be sent. The path filter and the secret policy
+    /// are part of the key as well, as they change the diff that is sent.
+    /// Summaries are stored under the provider and model that gave them.
+    pub(super) fn explain_cache_key(&self, git: &Git) -> Option<CacheKey> {
+        self.cache.as_ref()?;
+
+        let subject = match git {
+            Git::Commit(commit) => commit.full_hash.clone(),
+            Git::Range(range) => range
+                .commits
+                .iter()
+                .map(|commit| commit.full_hash.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            Git::Staged(staged) => cache::hash(&staged.diff),
+        };
+
+        let mut template = git.clone();
+        template.diff_mut().clear();
+        let mut prompt = AIPrompt::build_explain_prompt(&template, &self.config);
+        if self.config.output.value == OutputFormat::Json {
+            prompt = prompt.with_json_output();
+        }
+
+        let secrets = format!(
+            "{} [{}]",
+            config::value_name(self.config.secrets.value),
+            self.config.secret_patterns.value.join(", ")
+        );
+
+        Some(CacheKey::new(
+            &subject,
+            &format!(
+                "{}\n{}\n{}\n{secrets}",
+                prompt.system_prompt,
+                prompt.user_prompt,
+                self.filter.patterns()
+            ),
+        ))
+    }
+
+    /// The cached response by the first provider in the chain that has
+    /// one, as the chain would ask them in that order.
+    pub(super) fn cached_response(&self, key: Option<&CacheKey>) -> Option<CacheEntry> {
+        let (cache, key) = (self.cache.as_ref()?, key?);
+        self.provider
+            .answerers()
+            .find_map(|(provider, model)| cache.get(&key.by(provider, model)))
+    }
+
+    /// Stores a response, along with the provider and model that gave it.
+    /// The cache is an optimization, so failing to write it is not an error.
+    pub(super) fn cache_response(
+        &self,
+        key: Option<&CacheKey>,
+        git: &Git,
+        provider: &str,
+        model: &str,
+        response: &str,
+    ) {
+        let (Some(cache), Some(key)) = (&self.cache, key) else {
+            return;
+        };
+        if response.trim().is_empty() {
+            return;
+        }
+
+        let entry = CacheEntry {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            commit: match git {
+                Git::Commit(commit) => Some(commit.full_hash.clone()),
+                _ => None,
+            },
+            response: response.to_string(),
+        };
+        let _ = cache.put(&key.by(provider, model), &entry);
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1024 * 1024 => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+        bytes if bytes >= 1024 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        bytes => format!("{bytes} B"),
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs if secs >= 24 * 60 * 60 => format!("{} days ago", secs / (24 * 60 * 60)),
+        secs if secs >= 60 * 60 => format!("{} hours ago", secs / (60 * 60)),
+        secs if secs >= 60 => format!("{} minutes ago", secs / 60),
+        _ => "just now".to_string(),
+    }
+}
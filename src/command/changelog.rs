@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use super::LumenCommand;
+use crate::ai_prompt::AIPrompt;
+use crate::changelog::{self, Category, Changelog, ChangelogEntry};
+use crate::error::LumenError;
+use crate::git_commit::GitCommit;
+use crate::git_range::GitRange;
+use crate::git_repo::GitRepo;
+use crate::provider::AIProvider;
+
+impl LumenCommand {
+    pub async fn changelog(
+        &self,
+        range: String,
+        version: Option<String>,
+        prepend: Option<PathBuf>,
+    ) -> Result<(), LumenError> {
+        self.require_text_output("changelog")?;
+        let repo = GitRepo::open()?;
+        // Only the commit messages are classified, so the diff is not needed
+        let range = GitRange::log(&repo, range)?;
+
+        let mut entries: Vec<Option<ChangelogEntry>> = range
+            .commits
+            .iter()
+            .map(ChangelogEntry::from_conventional)
+            .collect();
+
+        // Commits without a Conventional Commit prefix are classified by the
+        // provider in a single request, with secrets redacted from their
+        // messages
+        let unclassified: Vec<(usize, GitCommit)> = range
+            .commits
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| entries[*i].is_none())
+            .map(|(i, commit)| Ok((i, self.redact_message(commit)?)))
+            .collect::<Result<_, LumenError>>()?;
+
+        if !unclassified.is_empty() {
+            let commits: Vec<&GitCommit> = unclassified.iter().map(|(_, commit)| commit).collect();
+            let prompt = AIPrompt::build_changelog_prompt(&commits, &self.config);
+            if self.provider.is_dry_run() {
+                return self.print_requests(&prompt, false, None);
+            }
+
+            let mut spinner = self.spinner("Classifying commits...");
+            let response = self.provider.complete(prompt).await;
+            spinner.clear();
+
+            let categories = changelog::parse_classification(&response?, commits.len());
+            for ((i, _), category) in unclassified.into_iter().zip(categories) {
+                entries[i] = Some(ChangelogEntry::new(
+                    &range.commits[i],
+                    category.unwrap_or(Category::Changed),
+                ));
+            }
+        }
+
+        let date = range
+            .commits
+            .last()
+            .map(|commit| commit.date.chars().take(10).collect())
+            .unwrap_or_default();
+
+        let section = Changelog {
+            version: version.or(range.tag(&repo)?.map(|tag| changelog::tag_version(&tag))),
+            date,
+            entries: entries.into_iter().flatten().collect(),
+        }
+        .render();
+
+        match prepend {
+            Some(path) => {
+                changelog::prepend(&path, &section)?;
+                println!("Updated {}", path.display());
+            }
+            None => print!("{section}"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::io::{self, Write};
+
+use super::{Git, LumenCommand};
+use crate::ai_prompt::{AIPrompt, ChatMessage, Role};
+use crate::error::LumenError;
+
+const HELP: &str = "Ask questions about the changes, or use:\n  \
+    /save [file]  save the transcript as Markdown\n  \
+    /help         show this help\n  \
+    /exit         quit (or press Ctrl-D)";
+
+impl LumenCommand {
+    /// Answers questions about a commit, a range or the staged changes,
+    /// keeping the diff and the earlier questions and answers as context.
+    pub async fn chat(&self, sha: Option<String>) -> Result<(), LumenError> {
+        self.require_text_output("chat")?;
+
+        let mut git = self.git(sha)?;
+        let diff = git.diff_mut();
+        *diff = self.condense_diff(&self.redact_secrets(diff)?).await?;
+
+        // The header is printed rather than paged, as the conversation
+        // follows it
+        if let Some(header) = git.header() {
+            println!("{}", self.markdown().render(&format!("{header}\n-----\n")));
+        }
+        println!("{HELP}\n");
+
+        let mut turns: Vec<ChatMessage> = Vec::new();
+        // The provider and model of each answer, as a fallback may answer
+        // some questions in place of the primary provider
+        let mut answered_by: Vec<(&'static str, String)> = Vec::new();
+        while let Some(line) = read_line()? {
+            let input = line.trim();
+            let (command, argument) = input
+                .split_once(char::is_whitespace)
+                .map_or((input, ""), |(command, argument)| {
+                    (command, argument.trim())
+                });
+
+            match command {
+                "" => continue,
+                "/exit" | "/quit" => break,
+                "/help" => println!("{HELP}\n"),
+                "/save" => {
+                    let path = match argument {
+                        "" => transcript_name(&git),
+                        path => path.to_string(),
+                    };
+                    match fs::write(&path, transcript(&git, &turns, &answered_by)) {
+                        Ok(()) => println!("Saved the transcript to {path}\n"),
+                        Err(err) => eprintln!("Error: Could not write '{path}': {err}\n"),
+                    }
+                }
+                command if command.starts_with('/') => {
+                    println!("Unknown command {command}, see /help\n")
+                }
+                _ => {
+                    let prompt = AIPrompt::build_chat_prompt(&git, &turns, input, &self.config);
+                    if self.provider.is_dry_run() {
+                        self.print_requests(&prompt, true, None)?;
+                        continue;
+                    }
+                    match self.stream_markdown(prompt, "Thinking...").await {
+                        Ok(answer) => {
+                            println!();
+                            turns.push(ChatMessage {
+                                role: Role::User,
+                                content: input.to_string(),
+                            });
+                            turns.push(ChatMessage {
+                                role: Role::Assistant,
+                                content: answer.completion.text,
+                            });
+                            answered_by.push((answer.provider, answer.model));
+                        }
+                        // The question can be asked again, so a failed
+                        // request does not end the conversation
+                        Err(err) => eprintln!("Error: {err}\n"),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The conversation as a Markdown document, starting with what it was about,
+/// with each answer followed by the provider and model that gave it.
+fn transcript(git: &Git, turns: &[ChatMessage], answered_by: &[(&str, String)]) -> String {
+    let title = match git {
+        Git::Commit(commit) => format!("commit {}", &commit.full_hash[..7]),
+        Git::Range(range) => format!("`{}`", range.range),
+        Git::Staged(_) => "the staged changes".to_string(),
+    };
+
+    let mut transcript = format!("# Chat about {title}\n\n");
+    if let Some(header) = git.header() {
+        transcript.push_str(&format!("{header}\n"));
+    }
+
+    let mut answerers = answered_by.iter();
+    for turn in turns {
+        match turn.role {
+            Role::User => transcript.push_str(&format!("\n---\n\n**You:** {}\n", turn.content)),
+            Role::Assistant => {
+                transcript.push_str(&format!("\n{}\n", turn.content.trim()));
+                if let Some((provider, model)) = answerers.next() {
+                    transcript.push_str(&format!("\n*Answered by {provider} ({model})*\n"));
+                }
+            }
+        }
+    }
+
+    transcript
+}
+
+/// Prompts for the next line of input, or `None` at the end of the input.
+fn read_line() -> Result<Option<String>, LumenError> {
+    print!("> ");
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+
+    Ok(Some(line))
+}
+
+/// Where `/save` writes the transcript without a file name: e.g.
+/// `lumen-chat-1a2b3c4.md` in the current directory.
+fn transcript_name(git: &Git) -> String {
+    let subject = match git {
+        Git::Commit(commit) => commit.full_hash[..7].to_string(),
+        Git::Range(range) => range
+            .range
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect(),
+        Git::Staged(_) => "staged".to_string(),
+    };
+
+    format!("lumen-chat-{subject}.md")
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+use super::LumenCommand;
+use crate::ai_prompt::AIPrompt;
+use crate::commit_message::CommitMessage;
+use crate::conventional_commit::ConventionalCommit;
+use crate::error::LumenError;
+use crate::git_repo::GitRepo;
+use crate::git_staged::GitStaged;
+use crate::provider::AIProvider;
+use crate::CommitFormat;
+
+/// How many times a message that fails validation is requested again before
+/// giving up.
+const MAX_CORRECTIONS: usize = 2;
+
+enum Choice {
+    Accept,
+    Edit,
+    Regenerate,
+    Abort,
+}
+
+impl LumenCommand {
+    pub async fn commit(&self, format: CommitFormat) -> Result<(), LumenError> {
+        self.require_text_output("commit")?;
+        let mut staged = GitStaged::new(&GitRepo::open()?, &self.filter)?;
+        staged.diff = self
+            .condense_diff(&self.redact_secrets(&staged.diff)?)
+            .await?;
+        let prompt = AIPrompt::build_commit_prompt(&staged, format, &self.config);
+        if self.provider.is_dry_run() {
+            return self.print_requests(&prompt, false, None);
+        }
+
+        // A file with an unpredictable name that only the user can read,
+        // removed when it is dropped
+        let message_file = tempfile::Builder::new()
+            .prefix("lumen-COMMIT_EDITMSG-")
+            .tempfile()?;
+
+        let mut message = self.generate_commit_message(&prompt, format).await?;
+
+        loop {
+            println!("\n{message}\n");
+
+            match ask_choice()? {
+                Choice::Accept => {
+                    fs::write(message_file.path(), &message)?;
+                    return Ok(GitStaged::commit(message_file.path())?);
+                }
+                Choice::Edit => {
+                    message = edit_message(message_file.path(), &message)?;
+                    if message.is_empty() {
+                        println!("Aborting commit due to empty commit message.");
+                        return Ok(());
+                    }
+                    if format == CommitFormat::Conventional {
+                        if let Err(err) = ConventionalCommit::parse(&message) {
+                            eprintln!("Warning: not a valid Conventional Commit ({err})");
+                        }
+                    }
+                }
+                Choice::Regenerate => {
+                    message = self.generate_commit_message(&prompt, format).await?;
+                }
+                Choice::Abort => {
+                    println!("Commit aborted.");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Prints a commit message for the staged changes without committing.
+    pub async fn suggest(&self, format: CommitFormat) -> Result<(), LumenError> {
+        if format == CommitFormat::Plain {
+            return self.explain(None).await;
+        }
+        self.require_text_output("suggest --format conventional")?;
+
+        let mut staged = GitStaged::new(&GitRepo::open()?, &self.filter)?;
+        staged.diff = self
+            .condense_diff(&self.redact_secrets(&staged.diff)?)
+            .await?;
+        let prompt = AIPrompt::build_commit_prompt(&staged, format, &self.config);
+        if self.provider.is_dry_run() {
+            return self.print_requests(&prompt, false, None);
+        }
+
+        let message = self.generate_commit_message(&prompt, format).await?;
+        println!("{message}");
+
+        Ok(())
+    }
+
+    pub(super) async fn generate_commit_message(
+        &self,
+        prompt: &AIPrompt,
+        format: CommitFormat,
+    ) -> Result<String, LumenError> {
+        let mut spinner = self.spinner("Generating commit message...");
+        let result = self.request_commit_message(prompt, format).await;
+        spinner.clear();
+
+        result
+    }
+
+    async fn request_commit_message(
+        &self,
+        prompt: &AIPrompt,
+        format: CommitFormat,
+    ) -> Result<String, LumenError> {
+        let mut prompt = prompt.clone();
+        let mut attempt = 0;
+
+        loop {
+            let message =
+                CommitMessage::parse(&self.provider.complete(prompt.clone()).await?).to_string();
+
+            if format == CommitFormat::Plain {
+                return Ok(message);
+            }
+
+            match ConventionalCommit::parse(&message) {
+                Ok(_) => return Ok(message),
+                Err(err) if attempt < MAX_CORRECTIONS => {
+                    attempt += 1;
+                    prompt = prompt.with_correction(&message, &err.to_string());
+                }
+                Err(err) => return Err(LumenError::InvalidConventionalCommit(err, message)),
+            }
+        }
+    }
+}
+
+fn ask_choice() -> Result<Choice, LumenError> {
+    loop {
+        print!("[a]ccept, [e]dit, [r]egenerate or [q]uit? ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            return Ok(Choice::Abort);
+        }
+
+        match input.trim().to_lowercase().as_str() {
+            "a" | "accept" | "y" | "yes" => return Ok(Choice::Accept),
+            "e" | "edit" => return Ok(Choice::Edit),
+            "r" | "regenerate" => return Ok(Choice::Regenerate),
+            "q" | "quit" | "n" | "no" => return Ok(Choice::Abort),
+            _ => continue,
+        }
+    }
+}
+
+/// Opens the message in `$VISUAL` or `$EDITOR` (falling back to `vi`) and
+/// returns the edited text.
+fn edit_message(message_file: &Path, message: &str) -> Result<String, LumenError> {
+    fs::write(message_file, message)?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // Run through the shell so that editors configured with arguments
+    // (e.g. `code --wait`) work as they do for git
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg(&editor)
+        .arg(message_file)
+        .status()?;
+
+    if !status.success() {
+        return Err(LumenError::UnknownError(
+            format!("editor '{editor}' exited with {status}").into(),
+        ));
+    }
+
+    Ok(fs::read_to_string(message_file)?.trim().to_string())
+}
@@ -0,0 +1,188 @@
+use std::io::IsTerminal;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use super::{Git, LumenCommand};
+use crate::ai_prompt::AIPrompt;
+use crate::error::LumenError;
+use crate::git_commit::GitCommit;
+use crate::summary::Summary;
+use crate::OutputFormat;
+
+impl LumenCommand {
+    pub async fn explain(&self, sha: Option<String>) -> Result<(), LumenError> {
+        let mut git = self.git(sha)?;
+
+        if self.config.output.value == OutputFormat::Json {
+            return self.explain_json(git).await;
+        }
+
+        let header = git.header().map(|header| format!("{header}\n-----\n"));
+
+        // The header and the summary are rendered as one document, so that
+        // they end up in the same pager
+        let document = |summary: &str| match &header {
+            Some(header) => format!("{header}\n{summary}"),
+            None => summary.to_string(),
+        };
+
+        let cache_key = self.explain_cache_key(&git);
+        if let Some(entry) = self.cached_response(cache_key.as_ref()) {
+            return self.print_markdown(&document(&entry.response));
+        }
+
+        // Without a pager the header is shown while the summary is generated
+        let streaming = std::io::stdout().is_terminal() && !self.use_pager();
+        if let (true, Some(header)) = (streaming && !self.provider.is_dry_run(), &header) {
+            self.print_markdown(header)?;
+        }
+
+        let diff = git.diff_mut();
+        *diff = self.condense_diff(&self.redact_secrets(diff)?).await?;
+
+        let prompt = AIPrompt::build_explain_prompt(&git, &self.config);
+        if self.provider.is_dry_run() {
+            return self.print_requests(&prompt, streaming, None);
+        }
+
+        // Stream the summary as it is generated when writing to a terminal,
+        // otherwise wait for the full result to pipe or page it
+        if streaming {
+            let answer = self
+                .stream_markdown(prompt, "Generating Summary...")
+                .await?;
+            self.cache_response(
+                cache_key.as_ref(),
+                &git,
+                answer.provider,
+                &answer.model,
+                &answer.completion.text,
+            );
+            return Ok(());
+        }
+
+        let mut spinner = self.spinner("Generating Summary...");
+        let answer = self.provider.answer(prompt).await;
+        if answer.is_err() {
+            spinner.clear();
+        }
+        let answer = answer?;
+        spinner.success("Done");
+        self.cache_response(
+            cache_key.as_ref(),
+            &git,
+            answer.provider,
+            &answer.model,
+            &answer.completion.text,
+        );
+        self.print_markdown(&document(&answer.completion.text))
+    }
+
+    /// Prints the summary as a JSON object along with what was summarized,
+    /// which provider and model answered, the token usage and the latency.
+    async fn explain_json(&self, mut git: Git) -> Result<(), LumenError> {
+        let cache_key = self.explain_cache_key(&git);
+        if let Some(entry) = self.cached_response(cache_key.as_ref()) {
+            let summary = Summary::parse(&entry.response);
+            let output = json_output(
+                &git,
+                &entry.provider,
+                &entry.model,
+                &summary,
+                json!({
+                    "usage": null,
+                    "latency_ms": null,
+                    "cached": true,
+                }),
+            );
+            println!("{output:#}");
+            return Ok(());
+        }
+
+        let diff = git.diff_mut();
+        *diff = self.condense_diff(&self.redact_secrets(diff)?).await?;
+
+        let prompt = AIPrompt::build_explain_prompt(&git, &self.config).with_json_output();
+        if self.provider.is_dry_run() {
+            return self.print_requests(&prompt, false, Some(&Summary::schema()));
+        }
+        let mut spinner = self.spinner("Generating Summary...");
+        let started = Instant::now();
+
+        let answer = self
+            .provider
+            .complete_json(prompt, &Summary::schema())
+            .await;
+        spinner.clear();
+        let answer = answer?;
+        let latency = started.elapsed();
+
+        self.cache_response(
+            cache_key.as_ref(),
+            &git,
+            answer.provider,
+            &answer.model,
+            &answer.completion.text,
+        );
+
+        let summary = Summary::parse(&answer.completion.text);
+        let output = json_output(
+            &git,
+            answer.provider,
+            &answer.model,
+            &summary,
+            json!({
+                "usage": answer.completion.usage,
+                "latency_ms": latency.as_millis() as u64,
+                "cached": false,
+            }),
+        );
+        println!("{output:#}");
+
+        Ok(())
+    }
+}
+
+/// The `--output json` object for a summary of `git`, followed by the
+/// fields in `stats`.
+fn json_output(git: &Git, provider: &str, model: &str, summary: &Summary, stats: Value) -> Value {
+    let commit = |commit: &GitCommit| {
+        json!({
+            "hash": commit.full_hash,
+            "author": { "name": commit.author_name, "email": commit.author_email },
+            "date": commit.date,
+            "message": commit.message,
+        })
+    };
+
+    let (files, commit, range) = match git {
+        Git::Commit(c) => (&c.files, commit(c), Value::Null),
+        Git::Range(range) => (
+            &range.files,
+            Value::Null,
+            json!({
+                "range": range.range,
+                "commits": range.commits.iter().map(commit).collect::<Vec<_>>(),
+            }),
+        ),
+        Git::Staged(staged) => (&staged.files, Value::Null, Value::Null),
+    };
+
+    let mut output = json!({
+        "commit": commit,
+        "range": range,
+        "staged": matches!(git, Git::Staged(_)),
+        "files": files,
+        "provider": provider,
+        "model": model,
+        "title": summary.title,
+        "summary": summary.summary,
+        "changes": summary.changes,
+    });
+    if let (Some(output), Value::Object(stats)) = (output.as_object_mut(), stats) {
+        output.extend(stats);
+    }
+
+    output
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::Path;
+
+use super::LumenCommand;
+use crate::ai_prompt::AIPrompt;
+use crate::error::LumenError;
+use crate::git_hook::GitHook;
+use crate::git_repo::GitRepo;
+use crate::git_staged::GitStaged;
+use crate::CommitFormat;
+
+impl LumenCommand {
+    pub fn install_hook(force: bool) -> Result<(), LumenError> {
+        let hook = GitHook::prepare_commit_msg(&GitRepo::open()?)?;
+        hook.install(force)?;
+        println!(
+            "Installed prepare-commit-msg hook at {}",
+            hook.path.display()
+        );
+        Ok(())
+    }
+
+    pub fn uninstall_hook() -> Result<(), LumenError> {
+        let hook = GitHook::prepare_commit_msg(&GitRepo::open()?)?;
+        hook.uninstall()?;
+        println!("Removed prepare-commit-msg hook at {}", hook.path.display());
+        Ok(())
+    }
+
+    /// Entry point of the `prepare-commit-msg` hook. A message is only
+    /// generated when git passes no source, that is for a plain `git commit`;
+    /// messages from `-m`, `-F`, templates, merges, squashes, `-c`, `-C` and
+    /// `--amend` are left untouched. Failures are reported without blocking
+    /// the commit.
+    pub async fn run_hook(
+        &self,
+        message_file: &Path,
+        source: Option<&str>,
+        format: CommitFormat,
+    ) -> Result<(), LumenError> {
+        if source.is_some_and(|source| !source.is_empty()) {
+            return Ok(());
+        }
+
+        if let Err(err) = self.fill_commit_message(message_file, format).await {
+            eprintln!("lumen: could not generate a commit message: {err}");
+        }
+
+        Ok(())
+    }
+
+    async fn fill_commit_message(
+        &self,
+        message_file: &Path,
+        format: CommitFormat,
+    ) -> Result<(), LumenError> {
+        let mut staged = GitStaged::new(&GitRepo::open()?, &self.filter)?;
+        staged.diff = self
+            .condense_diff(&self.redact_secrets(&staged.diff)?)
+            .await?;
+        let prompt = AIPrompt::build_commit_prompt(&staged, format, &self.config);
+        if self.provider.is_dry_run() {
+            return self.print_requests(&prompt, false, None);
+        }
+
+        let message = self.generate_commit_message(&prompt, format).await?;
+
+        // Keep the comments git has already written below the message
+        let existing = fs::read_to_string(message_file)?;
+        fs::write(message_file, format!("{message}\n{existing}"))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use super::LumenCommand;
+use crate::commit_picker::{CommitPicker, Pick};
+use crate::error::LumenError;
+use crate::git_repo::GitRepo;
+
+impl LumenCommand {
+    pub async fn list(&self) -> Result<(), LumenError> {
+        let picked = {
+            let repo = GitRepo::open()?;
+
+            // Summaries cached by the providers and models in the chain are
+            // shown along with their commits; without a readable cache there
+            // are none
+            let answerers: Vec<_> = self.provider.answerers().collect();
+            let summaries = match &self.cache {
+                Some(cache) => cache.commit_summaries(&answerers).unwrap_or_default(),
+                None => HashMap::new(),
+            };
+
+            CommitPicker::new(&repo, &summaries, self.color())?.run()?
+        };
+
+        match picked {
+            Some(Pick::Range(range)) => self.explain(Some(range)).await,
+            Some(Pick::Commits(hashes)) => {
+                for (i, hash) in hashes.into_iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    self.explain(Some(hash)).await?;
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use futures::StreamExt;
+use spinoff::{spinners, Color, Spinner, Streams};
+
+use crate::ai_prompt::AIPrompt;
+use crate::cache::ResponseCache;
+use crate::config::LumenConfig;
+use crate::error::LumenError;
+use crate::git_commit::GitCommit;
+use crate::git_range::GitRange;
+use crate::git_repo::GitRepo;
+use crate::git_staged::GitStaged;
+use crate::markdown::{self, Markdown};
+use crate::path_filter::PathFilter;
+use crate::prompt_template;
+use crate::provider::chain::{Answer, ProviderChain};
+use crate::provider::Completion;
+use crate::secrets::SecretScanner;
+use crate::OutputFormat;
+
+mod cache;
+mod changelog;
+mod chat;
+mod commit;
+mod explain;
+mod hook;
+mod list;
+mod models;
+mod pr;
+mod prompt;
+mod secrets;
+mod summarize;
+
+#[derive(Clone)]
+pub enum Git {
+    Commit(GitCommit),
+    Staged(GitStaged),
+    Range(GitRange),
+}
+
+impl Git {
+    /// What is being summarized, as a Markdown header: the hash, author,
+    /// date and message of a commit, or the commits in a range.
+    fn header(&self) -> Option<String> {
+        match self {
+            Git::Commit(commit) => Some(format!(
+                "`commit {}` | {} <{}> | {}\n\n{}",
+                commit.full_hash,
+                commit.author_name,
+                commit.author_email,
+                commit.date,
+                commit.message,
+            )),
+            Git::Range(range) => Some(format!(
+                "`{}` | {} commits\n\n{}",
+                range.range,
+                range.commits.len(),
+                range
+                    .commits
+                    .iter()
+                    .map(|commit| format!(
+                        "- `{}` {}",
+                        &commit.full_hash[..7],
+                        commit.message.lines().next().unwrap_or_default()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )),
+            Git::Staged(_) => None,
+        }
+    }
+
+    fn diff_mut(&mut self) -> &mut String {
+        match self {
+            Git::Commit(commit) => &mut commit.diff,
+            Git::Staged(staged) => &mut staged.diff,
+            Git::Range(range) => &mut range.diff,
+        }
+    }
+}
+
+pub struct LumenCommand {
+    provider: ProviderChain,
+    config: LumenConfig,
+    filter: PathFilter,
+    secrets: Option<SecretScanner>,
+    cache: Option<ResponseCache>,
+}
+
+impl LumenCommand {
+    pub fn new(provider: ProviderChain, config: LumenConfig) -> Result<Self, LumenError> {
+        prompt_template::validate(&config)?;
+        let filter = Self::path_filter(&config)?;
+        let secrets = Self::secret_scanner(&config)?;
+
+        // Without a cache directory responses are simply not cached. Dry runs
+        // skip the cache so that the request is shown even for cached commits
+        let cache = match config.cache.value && !provider.is_dry_run() {
+            true => ResponseCache::new(&config).ok(),
+            false => None,
+        };
+
+        Ok(LumenCommand {
+            provider,
+            config,
+            filter,
+            secrets,
+            cache,
+        })
+    }
+
+    fn path_filter(config: &LumenConfig) -> Result<PathFilter, LumenError> {
+        let mut exclude = Vec::new();
+        if let Some(repo_root) = LumenConfig::repo_root() {
+            exclude.extend(PathFilter::read_ignore_file(&repo_root)?);
+        }
+        exclude.extend(config.exclude.value.iter().cloned());
+
+        Ok(PathFilter::new(&config.include.value, &exclude)?)
+    }
+
+    /// What `sha` names: a range if it contains `..`, a commit otherwise, or
+    /// the staged changes without it.
+    fn git(&self, sha: Option<String>) -> Result<Git, LumenError> {
+        let repo = GitRepo::open()?;
+        Ok(match sha {
+            Some(range) if GitRange::is_range(&range) => {
+                Git::Range(GitRange::new(&repo, range, &self.filter)?)
+            }
+            Some(sha) => Git::Commit(GitCommit::new(&repo, sha, &self.filter)?),
+            None => Git::Staged(GitStaged::new(&repo, &self.filter)?),
+        })
+    }
+
+    /// A spinner shown while waiting for the provider. With JSON output it
+    /// is drawn on stderr, so that stdout can be parsed.
+    fn spinner(&self, message: &str) -> Spinner {
+        let stream = match self.config.output.value {
+            OutputFormat::Text => Streams::Stdout,
+            OutputFormat::Json => Streams::Stderr,
+        };
+
+        Spinner::new_with_stream(spinners::Dots, message.to_string(), Color::Blue, stream)
+    }
+
+    /// Fails with JSON output for commands that only print text.
+    fn require_text_output(&self, command: &str) -> Result<(), LumenError> {
+        match self.config.output.value {
+            OutputFormat::Text => Ok(()),
+            OutputFormat::Json => Err(LumenError::JsonOutputNotSupported(command.to_string())),
+        }
+    }
+
+    /// Whether output to stdout is colored.
+    fn color(&self) -> bool {
+        markdown::use_color(self.config.color.value, std::io::stdout().is_terminal())
+    }
+
+    /// The renderer for Markdown printed to stdout.
+    fn markdown(&self) -> Markdown {
+        Markdown::new(self.color(), markdown::terminal_width())
+    }
+
+    /// Whether output is shown in a pager rather than printed.
+    fn use_pager(&self) -> bool {
+        self.config.pager.value && std::io::stdout().is_terminal()
+    }
+
+    pub fn print_markdown(&self, content: &str) -> Result<(), LumenError> {
+        let rendered = self.markdown().render(content);
+        if !self.use_pager() {
+            println!("{rendered}");
+            return Ok(());
+        }
+
+        // `-R` shows colors, `-F` exits right away when the output fits on
+        // one screen and `-X` leaves it on the screen afterwards
+        let pager = std::env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| "less -FRX".to_string());
+
+        match Command::new("sh")
+            .arg("-c")
+            .arg(&pager)
+            .stdin(Stdio::piped())
+            .spawn()
+        {
+            Ok(mut child) => {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // The pager closes its input when it is quit early
+                    match writeln!(stdin, "{rendered}") {
+                        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+                            return Err(err.into())
+                        }
+                        _ => {}
+                    }
+                }
+                child.wait()?;
+            }
+            Err(_) => println!("{rendered}"),
+        }
+        Ok(())
+    }
+
+    /// Prints the requests completing `prompt` sends, for `--dry-run`: the
+    /// one to the configured provider, followed by those to its fallbacks.
+    fn print_requests(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<(), LumenError> {
+        let requests = self
+            .provider
+            .dry_run(prompt, stream, schema)
+            .map_err(|err| LumenError::UnknownError(err.into()))?;
+
+        for (i, request) in requests.iter().enumerate() {
+            match i {
+                0 => println!("Request to {} ({}):", request.provider, request.model),
+                _ => println!(
+                    "Request to {} ({}), if the providers above are unavailable:",
+                    request.provider, request.model
+                ),
+            }
+            println!("{request}\n");
+        }
+
+        Ok(())
+    }
+
+    /// Prints the completion of `prompt` as it is generated, rendering each
+    /// Markdown block as soon as it is complete, and returns the full
+    /// response along with who gave it. The spinner is shown until the first
+    /// block is.
+    async fn stream_markdown(&self, prompt: AIPrompt, message: &str) -> Result<Answer, LumenError> {
+        let mut spinner = self.spinner(message);
+        let answer = self.provider.answer_stream(prompt).await;
+        if answer.is_err() {
+            spinner.clear();
+        }
+        let answer = answer?;
+        let mut stream = answer.stream;
+        let markdown = self.markdown();
+        let mut rendered = markdown.stream();
+        let mut stdout = io::stdout();
+        let mut started = false;
+        let mut response = String::new();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) if !started => {
+                    spinner.clear();
+                    return Err(err.into());
+                }
+                Err(err) => return Err(err.into()),
+            };
+            response.push_str(&chunk);
+
+            if let Some(blocks) = rendered.push(&chunk) {
+                if !started {
+                    spinner.clear();
+                    started = true;
+                }
+                write!(stdout, "{blocks}")?;
+                stdout.flush()?;
+            }
+        }
+
+        if !started {
+            spinner.clear();
+        }
+        if let Some(blocks) = rendered.finish() {
+            write!(stdout, "{blocks}")?;
+        }
+
+        Ok(Answer {
+            provider: answer.provider,
+            model: answer.model,
+            completion: Completion {
+                text: response,
+                usage: None,
+            },
+        })
+    }
+}
@@ -0,0 +1,19 @@
+use super::LumenCommand;
+use crate::error::LumenError;
+use crate::OutputFormat;
+
+impl LumenCommand {
+    pub async fn models(&self) -> Result<(), LumenError> {
+        let models = self.provider.list_models().await?;
+        if self.config.output.value == OutputFormat::Json {
+            println!("{:#}", serde_json::json!(models));
+            return Ok(());
+        }
+
+        for model in models {
+            println!("{model}");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use super::LumenCommand;
+use crate::ai_prompt::AIPrompt;
+use crate::config::LumenConfig;
+use crate::error::LumenError;
+use crate::git_branch::GitBranch;
+use crate::git_range::GitRange;
+use crate::git_repo::GitRepo;
+use crate::pr_template::PrTemplate;
+use crate::provider::AIProvider;
+
+/// How many times a description that does not follow the template is
+/// requested again before giving up.
+const MAX_CORRECTIONS: usize = 2;
+
+impl LumenCommand {
+    pub async fn pr(
+        &self,
+        base: Option<String>,
+        template: Option<PathBuf>,
+    ) -> Result<(), LumenError> {
+        self.require_text_output("pr")?;
+        let repo = GitRepo::open()?;
+        let branch = GitBranch::current(&repo, base.or_else(|| self.config.pr_base.value.clone()))?;
+        let mut range = GitRange::new(
+            &repo,
+            format!("{}..{}", branch.base, branch.name),
+            &self.filter,
+        )?;
+        range.diff = self
+            .condense_diff(&self.redact_secrets(&range.diff)?)
+            .await?;
+
+        let template_path = template.or_else(|| self.config.pr_template.value.clone());
+        let template = PrTemplate::load(
+            template_path.as_deref(),
+            LumenConfig::repo_root().as_deref(),
+        )?;
+
+        let mut prompt = AIPrompt::build_pr_prompt(&branch, &range, &template, &self.config);
+        if self.provider.is_dry_run() {
+            return self.print_requests(&prompt, false, None);
+        }
+
+        let mut spinner = self.spinner("Generating pull request description...");
+        let mut attempt = 0;
+
+        let description = loop {
+            let description = match self.provider.complete(prompt.clone()).await {
+                Ok(description) => description.trim().to_string(),
+                Err(err) => {
+                    spinner.clear();
+                    return Err(err.into());
+                }
+            };
+
+            let missing = template.missing_headings(&description);
+            if missing.is_empty() {
+                break description;
+            }
+            if attempt == MAX_CORRECTIONS {
+                spinner.clear();
+                return Err(LumenError::PrTemplateMismatch(missing, description));
+            }
+
+            attempt += 1;
+            prompt = prompt.with_correction(
+                &description,
+                &format!("it is missing the headings {}", missing.join(", ")),
+            );
+        };
+
+        spinner.clear();
+        println!("{description}");
+
+        Ok(())
+    }
+}
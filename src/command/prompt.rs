@@ -0,0 +1,92 @@
+use super::{secrets, Git, LumenCommand};
+use crate::ai_prompt::AIPrompt;
+use crate::changelog::ChangelogEntry;
+use crate::config::LumenConfig;
+use crate::error::LumenError;
+use crate::git_branch::GitBranch;
+use crate::git_commit::GitCommit;
+use crate::git_range::GitRange;
+use crate::git_repo::GitRepo;
+use crate::git_staged::GitStaged;
+use crate::pr_template::PrTemplate;
+use crate::prompt_template::{self, PromptTemplate, PromptTemplateError};
+
+impl LumenCommand {
+    /// Prints the prompt `name` as it would be sent, rendered with the
+    /// commit or range `rev`, the staged changes or the current branch.
+    /// Large diffs are shown in full rather than summarized, but with
+    /// secrets redacted.
+    pub fn show_prompt(
+        config: &LumenConfig,
+        name: &str,
+        rev: Option<String>,
+    ) -> Result<(), LumenError> {
+        prompt_template::validate(config)?;
+        let filter = Self::path_filter(config)?;
+        let repo = GitRepo::open()?;
+        let scanner = Self::secret_scanner(config)?;
+        let redact = |diff: &str| secrets::redact_secrets(scanner.as_ref(), config, diff);
+        let range = |rev: String| {
+            let mut range = GitRange::new(&repo, rev, &filter)?;
+            range.diff = redact(&range.diff)?;
+            Ok::<_, LumenError>(range)
+        };
+        let required = |rev: Option<String>| {
+            rev.ok_or_else(|| PromptTemplateError::RangeRequired(name.to_string()))
+        };
+        let staged = || {
+            let mut staged = GitStaged::new(&repo, &filter)?;
+            staged.diff = redact(&staged.diff)?;
+            Ok::<_, LumenError>(staged)
+        };
+
+        let prompt = match name {
+            "explain" => {
+                let sha = rev.unwrap_or_else(|| "HEAD".to_string());
+                let mut commit = GitCommit::new(&repo, sha, &filter)?;
+                commit.diff = redact(&commit.diff)?;
+                AIPrompt::build_explain_prompt(&Git::Commit(commit), config)
+            }
+            "suggest" => AIPrompt::build_explain_prompt(&Git::Staged(staged()?), config),
+            "range" => AIPrompt::build_explain_prompt(&Git::Range(range(required(rev)?)?), config),
+            "commit" => AIPrompt::build_commit_prompt(&staged()?, config.format.value, config),
+            "pr" => {
+                let branch = GitBranch::current(&repo, config.pr_base.value.clone())?;
+                let range = range(format!("{}..{}", branch.base, branch.name))?;
+                let template = PrTemplate::load(
+                    config.pr_template.value.as_deref(),
+                    LumenConfig::repo_root().as_deref(),
+                )?;
+                AIPrompt::build_pr_prompt(&branch, &range, &template, config)
+            }
+            "changelog" => {
+                // Only the commit messages are sent, not the diff
+                let range = GitRange::log(&repo, required(rev)?)?;
+                let commits = range
+                    .commits
+                    .iter()
+                    .filter(|commit| ChangelogEntry::from_conventional(commit).is_none())
+                    .map(|commit| secrets::redact_message(scanner.as_ref(), config, commit))
+                    .collect::<Result<Vec<_>, _>>()?;
+                AIPrompt::build_changelog_prompt(&commits.iter().collect::<Vec<_>>(), config)
+            }
+            // The chunk and merge prompts are filled in while summarizing a
+            // diff and the chat prompt with each question, so they are shown
+            // as templates
+            name => {
+                let template = PromptTemplate::get(name, config)?;
+                AIPrompt {
+                    system_prompt: template.system,
+                    user_prompt: template.user,
+                    history: Vec::new(),
+                }
+            }
+        };
+
+        println!(
+            "System:\n{}\n\nUser:\n{}",
+            prompt.system_prompt, prompt.user_prompt
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use super::LumenCommand;
+use crate::config::LumenConfig;
+use crate::error::LumenError;
+use crate::git_commit::GitCommit;
+use crate::secrets::{self, SecretScanner};
+use crate::SecretPolicy;
+
+impl LumenCommand {
+    /// The scanner for the configured secret patterns, or `None` when
+    /// secrets are allowed through.
+    pub(super) fn secret_scanner(
+        config: &LumenConfig,
+    ) -> Result<Option<SecretScanner>, LumenError> {
+        match config.secrets.value {
+            SecretPolicy::Allow => Ok(None),
+            _ => Ok(Some(SecretScanner::new(&config.secret_patterns.value)?)),
+        }
+    }
+
+    /// Replaces the secrets in `diff` before it is sent, and reports them.
+    pub(super) fn redact_secrets(&self, diff: &str) -> Result<String, LumenError> {
+        redact_secrets(self.secrets.as_ref(), &self.config, diff)
+    }
+
+    /// `commit` with the secrets in its message replaced, reporting them.
+    pub(super) fn redact_message(&self, commit: &GitCommit) -> Result<GitCommit, LumenError> {
+        redact_message(self.secrets.as_ref(), &self.config, commit)
+    }
+}
+
+/// Redacts `diff` with `scanner`, reporting what was redacted on stderr, or
+/// refuses to go on if the config says so.
+pub(super) fn redact_secrets(
+    scanner: Option<&SecretScanner>,
+    config: &LumenConfig,
+    diff: &str,
+) -> Result<String, LumenError> {
+    redact(scanner, config, diff, "the diff")
+}
+
+/// [`redact_secrets`] for the message of `commit`, which is sent without
+/// its diff when commits are classified for a changelog.
+pub(super) fn redact_message(
+    scanner: Option<&SecretScanner>,
+    config: &LumenConfig,
+    commit: &GitCommit,
+) -> Result<GitCommit, LumenError> {
+    let source = format!("the message of {}", &commit.full_hash[..7]);
+    Ok(GitCommit {
+        message: redact(scanner, config, &commit.message, &source)?,
+        ..commit.clone()
+    })
+}
+
+fn redact(
+    scanner: Option<&SecretScanner>,
+    config: &LumenConfig,
+    text: &str,
+    source: &str,
+) -> Result<String, LumenError> {
+    let Some(scanner) = scanner else {
+        return Ok(text.to_string());
+    };
+
+    let (redacted, found) = scanner.check(text, config.secrets.value)?;
+    if found.is_empty() {
+        return Ok(redacted);
+    }
+
+    eprintln!(
+        "Warning: redacted {} from {source}:\n{}",
+        match found.len() {
+            1 => "a secret".to_string(),
+            count => format!("{count} secrets"),
+        },
+        secrets::list(&found)
+    );
+
+    Ok(redacted)
+}
@@ -0,0 +1,165 @@
+use spinoff::Spinner;
+
+use super::LumenCommand;
+use crate::ai_prompt::AIPrompt;
+use crate::diff;
+use crate::error::LumenError;
+use crate::provider::AIProvider;
+
+/// Tokens kept free for the response when `max_tokens` is not configured.
+const RESPONSE_TOKENS: u32 = 1024;
+
+/// Tokens kept free for the instructions and commit messages that are sent
+/// along with the diff.
+const PROMPT_TOKENS: u32 = 1024;
+
+impl LumenCommand {
+    /// Returns `diff` unchanged if it fits in the model's context window.
+    /// Otherwise the diff is split into chunks that are summarized one by
+    /// one, and the summaries are merged until they fit, so that they can be
+    /// sent in place of the diff.
+    pub(super) async fn condense_diff(&self, diff: &str) -> Result<String, LumenError> {
+        let budget = self.diff_budget();
+        if diff::estimate_tokens(diff) <= budget {
+            return Ok(diff.to_string());
+        }
+
+        let chunks = diff::chunk(diff, budget);
+        let summaries = match self.provider.is_dry_run() {
+            true => self.print_chunk_requests(&chunks)?,
+            false => {
+                let mut spinner = self.spinner("Summarizing large diff...");
+                let result = self.summarize_chunks(&chunks, budget, &mut spinner).await;
+                spinner.clear();
+                result?
+            }
+        };
+
+        Ok(format!(
+            "The diff is too large to include in full. \
+             These are summaries of its {} parts:\n\n{}",
+            chunks.len(),
+            summaries.join("\n\n")
+        ))
+    }
+
+    /// Prints the request summarizing each chunk, for `--dry-run`, and
+    /// returns placeholders for the summaries. Merging the summaries depends
+    /// on how long they turn out, so those requests are not shown.
+    fn print_chunk_requests(&self, chunks: &[String]) -> Result<Vec<String>, LumenError> {
+        let mut summaries = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            println!(
+                "Part {} of {} of the diff is summarized first.",
+                i + 1,
+                chunks.len()
+            );
+            let prompt = AIPrompt::build_chunk_prompt(chunk, i + 1, chunks.len(), &self.config);
+            self.print_requests(&prompt, false, None)?;
+            summaries.push(format!("<summary of part {}>", i + 1));
+        }
+
+        Ok(summaries)
+    }
+
+    async fn summarize_chunks(
+        &self,
+        chunks: &[String],
+        budget: usize,
+        spinner: &mut Spinner,
+    ) -> Result<Vec<String>, LumenError> {
+        let mut summaries = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            spinner.update_text(format!("Summarizing part {} of {}...", i + 1, chunks.len()));
+            let prompt = AIPrompt::build_chunk_prompt(chunk, i + 1, chunks.len(), &self.config);
+            summaries.push(self.provider.complete(prompt).await?.trim().to_string());
+        }
+
+        while summaries.len() > 1 && diff::estimate_tokens(&summaries.join("\n\n")) > budget {
+            spinner.update_text(format!("Merging {} summaries...", summaries.len()));
+
+            let mut merged = Vec::new();
+            for group in group_summaries(summaries, budget) {
+                let prompt = AIPrompt::build_merge_prompt(&group, &self.config);
+                merged.push(self.provider.complete(prompt).await?.trim().to_string());
+            }
+            summaries = merged;
+        }
+
+        Ok(summaries)
+    }
+
+    /// How many tokens of diff fit in a single request.
+    fn diff_budget(&self) -> usize {
+        diff_budget(
+            self.provider.context_window(&self.config),
+            self.config.max_tokens.value,
+        )
+    }
+}
+
+/// How many tokens of diff fit in `context_window` next to the prompt and a
+/// response of `max_tokens`.
+fn diff_budget(context_window: u32, max_tokens: Option<u32>) -> usize {
+    let reserved = max_tokens.unwrap_or(RESPONSE_TOKENS) + PROMPT_TOKENS;
+
+    context_window.saturating_sub(reserved).max(PROMPT_TOKENS) as usize
+}
+
+/// Groups consecutive summaries so that each group fits in `budget`. Every
+/// group holds at least two summaries, so that merging always makes progress.
+fn group_summaries(summaries: Vec<String>, budget: usize) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+
+    for summary in summaries {
+        match groups.last_mut() {
+            Some(group)
+                if group.len() < 2
+                    || diff::estimate_tokens(&group.join("\n\n"))
+                        + diff::estimate_tokens(&summary)
+                        <= budget =>
+            {
+                group.push(summary)
+            }
+            _ => groups.push(vec![summary]),
+        }
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_leaves_room_for_prompt_and_response() {
+        assert_eq!(diff_budget(8_192, None), 8_192 - 1024 - 1024);
+        assert_eq!(diff_budget(128_000, Some(4_000)), 128_000 - 4_000 - 1024);
+    }
+
+    #[test]
+    fn budget_never_drops_below_the_prompt_reserve() {
+        assert_eq!(diff_budget(1_000, None), 1024);
+        assert_eq!(diff_budget(8_192, Some(100_000)), 1024);
+    }
+
+    #[test]
+    fn groups_fit_in_the_budget() {
+        let summaries = vec!["a".repeat(400); 5];
+        let groups = group_summaries(summaries, 250);
+
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 1]);
+        for group in &groups[..2] {
+            assert!(diff::estimate_tokens(&group.join("\n\n")) <= 250);
+        }
+    }
+
+    #[test]
+    fn groups_hold_two_summaries_even_over_the_budget() {
+        let summaries = vec!["a".repeat(4_000); 3];
+        let groups = group_summaries(summaries, 100);
+
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+    }
+}
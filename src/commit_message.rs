@@ -0,0 +1,191 @@
+use crate::conventional_commit;
+
+const SUBJECT_MAX_WIDTH: usize = 72;
+const BODY_WIDTH: usize = 72;
+
+/// A commit message normalized to git conventions: a short subject line,
+/// a blank line, and a body wrapped at 72 columns.
+#[derive(Clone, Debug)]
+pub struct CommitMessage {
+    pub subject: String,
+    pub body: String,
+}
+
+impl CommitMessage {
+    /// Normalizes a message returned by a provider. Code fences and
+    /// surrounding whitespace are stripped, the subject is shortened on a word
+    /// boundary if it is too long, and every body paragraph is re-wrapped.
+    pub fn parse(raw: &str) -> Self {
+        let lines: Vec<&str> = raw
+            .trim()
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("```"))
+            .collect();
+
+        let mut lines = lines.into_iter().skip_while(|line| line.trim().is_empty());
+        let subject = lines.next().unwrap_or_default().trim();
+        let subject = subject.trim_end_matches('.').to_string();
+        let body: Vec<&str> = lines.collect();
+
+        CommitMessage {
+            subject: truncate_subject(&subject),
+            body: wrap_body(&body.join("\n")),
+        }
+    }
+}
+
+impl std::fmt::Display for CommitMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.body.is_empty() {
+            write!(f, "{}", self.subject)
+        } else {
+            write!(f, "{}\n\n{}", self.subject, self.body)
+        }
+    }
+}
+
+fn truncate_subject(subject: &str) -> String {
+    if subject.chars().count() <= SUBJECT_MAX_WIDTH {
+        return subject.to_string();
+    }
+
+    let mut truncated = String::new();
+    for word in subject.split_whitespace() {
+        let width = truncated.chars().count() + word.chars().count() + 1;
+        if !truncated.is_empty() && width > SUBJECT_MAX_WIDTH {
+            break;
+        }
+        if !truncated.is_empty() {
+            truncated.push(' ');
+        }
+        truncated.push_str(word);
+    }
+
+    truncated.chars().take(SUBJECT_MAX_WIDTH).collect()
+}
+
+/// Re-wraps each paragraph of the body. List items are wrapped individually
+/// with a hanging indent so that bullets stay aligned, and trailer lines such
+/// as `BREAKING CHANGE: ...` are kept on lines of their own.
+fn wrap_body(body: &str) -> String {
+    let mut paragraphs = Vec::new();
+
+    for paragraph in body.trim().split("\n\n") {
+        let paragraph = paragraph.trim_matches('\n');
+        if paragraph.trim().is_empty() {
+            continue;
+        }
+
+        let mut items: Vec<String> = Vec::new();
+        for line in paragraph.lines() {
+            let trimmed = line.trim();
+            match items.last_mut() {
+                Some(item) if !starts_item(trimmed) => {
+                    item.push(' ');
+                    item.push_str(trimmed);
+                }
+                _ => items.push(trimmed.to_string()),
+            }
+        }
+
+        let wrapped: Vec<String> = items.iter().map(|item| wrap_item(item)).collect();
+        paragraphs.push(wrapped.join("\n"));
+    }
+
+    paragraphs.join("\n\n")
+}
+
+fn is_list_item(line: &str) -> bool {
+    line.starts_with("- ") || line.starts_with("* ")
+}
+
+fn starts_item(line: &str) -> bool {
+    is_list_item(line) || conventional_commit::parse_footer_line(line).is_some()
+}
+
+fn wrap_item(item: &str) -> String {
+    let indent = if is_list_item(item) { "  " } else { "" };
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in item.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + word.chars().count() + 1 > BODY_WIDTH {
+            lines.push(std::mem::take(&mut current));
+            current.push_str(indent);
+        } else if !current.is_empty() && current != indent {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+
+    if !current.trim().is_empty() {
+        lines.push(current);
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn widths(text: &str) -> Vec<usize> {
+        text.lines().map(|line| line.chars().count()).collect()
+    }
+
+    #[test]
+    fn shortens_long_subjects_on_a_word_boundary() {
+        let subject = "feat(i18n): übersetze die Fehlermeldungen für Zeitüberschreitungen und Wiederholungsversuche";
+        let truncated = truncate_subject(subject);
+
+        assert_eq!(
+            truncated,
+            "feat(i18n): übersetze die Fehlermeldungen für Zeitüberschreitungen und"
+        );
+        assert!(truncated.chars().count() <= SUBJECT_MAX_WIDTH);
+        assert_eq!(truncate_subject("fix: short"), "fix: short");
+    }
+
+    #[test]
+    fn cuts_a_single_long_word_between_characters() {
+        let subject = "é".repeat(100);
+        assert_eq!(truncate_subject(&subject), "é".repeat(SUBJECT_MAX_WIDTH));
+    }
+
+    #[test]
+    fn wraps_the_body_at_72_columns() {
+        let body = "Retries are now spread out with a backoff that doubles after every failed attempt, so providers that are briefly overloaded recover.\n\n- Honour the Retry-After header when a provider sends one instead of guessing\nBREAKING CHANGE: `retries` now counts retries rather than attempts";
+        let wrapped = wrap_body(body);
+
+        assert_eq!(
+            wrapped,
+            "Retries are now spread out with a backoff that doubles after every\n\
+             failed attempt, so providers that are briefly overloaded recover.\n\
+             \n\
+             - Honour the Retry-After header when a provider sends one instead of\n  \
+             guessing\n\
+             BREAKING CHANGE: `retries` now counts retries rather than attempts"
+        );
+        assert!(widths(&wrapped).iter().all(|&width| width <= BODY_WIDTH));
+    }
+
+    #[test]
+    fn keeps_one_blank_line_after_the_subject() {
+        let message = CommitMessage::parse(
+            "```\nfix: stop retrying failed requests.\n\n\nThe retry loop never ended.\n```\n",
+        );
+        assert_eq!(message.subject, "fix: stop retrying failed requests");
+        assert_eq!(message.body, "The retry loop never ended.");
+        assert_eq!(
+            message.to_string(),
+            "fix: stop retrying failed requests\n\nThe retry loop never ended."
+        );
+
+        let message = CommitMessage::parse("fix: stop retrying\nThe retry loop never ended.");
+        assert_eq!(
+            message.to_string(),
+            "fix: stop retrying\n\nThe retry loop never ended."
+        );
+        assert_eq!(CommitMessage::parse("fix: typo\n").to_string(), "fix: typo");
+    }
+}
@@ -0,0 +1,589 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, IsTerminal};
+use std::ops::ControlFlow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::git_repo::{self, GitRepo, GitRepoError};
+use crate::summary::Summary;
+
+/// How many commits, counting back from `HEAD`, can be picked from.
+const LOG_LIMIT: usize = 5000;
+
+/// How far Page Up and Page Down move the cursor.
+const PAGE: isize = 10;
+
+/// From this width on the preview is shown next to the list rather than
+/// below it.
+const SIDE_BY_SIDE_WIDTH: u16 = 120;
+
+#[derive(Debug, Clone)]
+pub enum CommitPickerError {
+    NotATerminal,
+    NoCommits,
+    TerminalError(String),
+    GitRepoError(GitRepoError),
+}
+
+impl From<io::Error> for CommitPickerError {
+    fn from(err: io::Error) -> CommitPickerError {
+        CommitPickerError::TerminalError(err.to_string())
+    }
+}
+
+impl From<GitRepoError> for CommitPickerError {
+    fn from(err: GitRepoError) -> CommitPickerError {
+        CommitPickerError::GitRepoError(err)
+    }
+}
+
+impl std::fmt::Display for CommitPickerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommitPickerError::NotATerminal => write!(
+                f,
+                "`list` needs an interactive terminal; use `lumen explain <sha>` instead"
+            ),
+            CommitPickerError::NoCommits => write!(f, "There are no commits to pick from"),
+            CommitPickerError::TerminalError(err) => write!(f, "Terminal error: {err}"),
+            CommitPickerError::GitRepoError(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// What was picked in a [`CommitPicker`].
+pub enum Pick {
+    /// Commits to explain one by one, oldest first.
+    Commits(Vec<String>),
+    /// A range to explain as a whole.
+    Range(String),
+}
+
+/// A commit in the picker.
+struct Entry {
+    hash: String,
+    subject: String,
+    author: String,
+    age: String,
+    /// The summary of an earlier `explain`, if it is still cached.
+    summary: Option<Summary>,
+    /// What the query is matched against: the short hash, the subject and
+    /// the author.
+    haystack: String,
+}
+
+/// A full-screen list of recent commits to fuzzy search, with a preview of
+/// the highlighted one. Any number of commits can be marked to explain them
+/// one by one, or together as the range they span.
+pub struct CommitPicker<'r> {
+    repo: &'r GitRepo,
+    entries: Vec<Entry>,
+    color: bool,
+    matcher: SkimMatcherV2,
+    query: String,
+    /// Indices of the entries matching the query, best match first.
+    matches: Vec<usize>,
+    list: ListState,
+    /// Indices of the marked entries; lower indices are newer commits.
+    marked: BTreeSet<usize>,
+    previews: HashMap<usize, String>,
+    preview_scroll: u16,
+    status: Option<String>,
+}
+
+impl<'r> CommitPicker<'r> {
+    /// A picker over the recent commits of `repo`. `summaries` are cached
+    /// summaries by commit hash, shown along with the commits they belong
+    /// to.
+    pub fn new(
+        repo: &'r GitRepo,
+        summaries: &HashMap<String, String>,
+        color: bool,
+    ) -> Result<Self, CommitPickerError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs() as i64)
+            .unwrap_or_default();
+
+        let entries: Vec<Entry> = repo
+            .log(LOG_LIMIT)?
+            .iter()
+            .map(|commit| {
+                let hash = commit.id().to_string();
+                let subject = String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default());
+                let author = commit.author().name().unwrap_or_default().to_string();
+
+                Entry {
+                    haystack: format!("{} {subject} {author}", &hash[..7]),
+                    age: relative_age(now - commit.time().seconds()),
+                    summary: summaries
+                        .get(&hash)
+                        .map(|response| Summary::parse(response)),
+                    hash,
+                    subject: subject.into_owned(),
+                    author,
+                }
+            })
+            .collect();
+        if entries.is_empty() {
+            return Err(CommitPickerError::NoCommits);
+        }
+
+        let mut picker = CommitPicker {
+            repo,
+            entries,
+            color,
+            matcher: SkimMatcherV2::default(),
+            query: String::new(),
+            matches: Vec::new(),
+            list: ListState::default(),
+            marked: BTreeSet::new(),
+            previews: HashMap::new(),
+            preview_scroll: 0,
+            status: None,
+        };
+        picker.search();
+
+        Ok(picker)
+    }
+
+    /// Shows the picker until commits are picked, or `None` once it is
+    /// closed without picking any.
+    pub fn run(mut self) -> Result<Option<Pick>, CommitPickerError> {
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return Err(CommitPickerError::NotATerminal);
+        }
+
+        let mut terminal = ratatui::try_init()?;
+        let picked = self.event_loop(&mut terminal);
+        ratatui::try_restore()?;
+
+        picked
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<Option<Pick>, CommitPickerError> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if let ControlFlow::Break(picked) = self.handle_key(key) {
+                        return Ok(picked);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> ControlFlow<Option<Pick>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        self.status = None;
+
+        match key.code {
+            KeyCode::Esc => return ControlFlow::Break(None),
+            KeyCode::Char('c') if ctrl => return ControlFlow::Break(None),
+            KeyCode::Enter => {
+                let hashes: Vec<String> = self
+                    .picked()
+                    .into_iter()
+                    .map(|i| self.entries[i].hash.clone())
+                    .collect();
+                if !hashes.is_empty() {
+                    return ControlFlow::Break(Some(Pick::Commits(hashes)));
+                }
+            }
+            KeyCode::Char('r') if ctrl => match self.range() {
+                Ok(Some(range)) => return ControlFlow::Break(Some(Pick::Range(range))),
+                Ok(None) => {}
+                Err(err) => self.status = Some(err),
+            },
+            KeyCode::Up if shift => self.preview_scroll = self.preview_scroll.saturating_sub(1),
+            KeyCode::Down if shift => self.preview_scroll = self.preview_scroll.saturating_add(1),
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('p') if ctrl => self.move_cursor(-1),
+            KeyCode::Char('n') if ctrl => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-PAGE),
+            KeyCode::PageDown => self.move_cursor(PAGE),
+            KeyCode::Tab => {
+                self.toggle_mark();
+                self.move_cursor(1);
+            }
+            KeyCode::BackTab => {
+                self.toggle_mark();
+                self.move_cursor(-1);
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.search();
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.search();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.search();
+            }
+            _ => {}
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Matches the entries against the query. A query that starts a commit
+    /// hash matches that commit first; otherwise the best fuzzy matches come
+    /// first, and the most recent of equally good ones.
+    fn search(&mut self) {
+        let query = self.query.to_lowercase();
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| match entry.hash.starts_with(&query) {
+                true => Some((i64::MAX, i)),
+                false => self
+                    .matcher
+                    .fuzzy_match(&entry.haystack, &self.query)
+                    .map(|score| (score, i)),
+            })
+            .collect();
+        scored.sort_by_key(|&(score, i)| (Reverse(score), i));
+
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.list.select((!self.matches.is_empty()).then_some(0));
+        self.preview_scroll = 0;
+    }
+
+    fn highlighted(&self) -> Option<usize> {
+        self.list
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .copied()
+    }
+
+    fn move_cursor(&mut self, by: isize) {
+        let Some(selected) = self.list.selected() else {
+            return;
+        };
+
+        let last = self.matches.len().saturating_sub(1) as isize;
+        self.list
+            .select(Some((selected as isize + by).clamp(0, last) as usize));
+        self.preview_scroll = 0;
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(i) = self.highlighted() {
+            if !self.marked.remove(&i) {
+                self.marked.insert(i);
+            }
+        }
+    }
+
+    /// The marked entries, or the highlighted one if none are, oldest first.
+    fn picked(&self) -> Vec<usize> {
+        match self.marked.is_empty() {
+            true => self.highlighted().into_iter().collect(),
+            false => self.marked.iter().rev().copied().collect(),
+        }
+    }
+
+    /// The range from the oldest picked commit to the newest, including
+    /// both and every commit in between.
+    fn range(&self) -> Result<Option<String>, String> {
+        let picked = self.picked();
+        let (Some(&oldest), Some(&newest)) = (picked.first(), picked.last()) else {
+            return Ok(None);
+        };
+        let (oldest, newest) = (&self.entries[oldest].hash, &self.entries[newest].hash);
+
+        let root = self
+            .repo
+            .commit(oldest)
+            .map(|commit| commit.parent_count() == 0)
+            .map_err(|err| err.to_string())?;
+        if root {
+            return Err("A range cannot start at the root commit".to_string());
+        }
+
+        Ok(Some(format!("{}^..{}", &oldest[..12], &newest[..12])))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search, body, keys] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list, preview] = match body.width >= SIDE_BY_SIDE_WIDTH {
+            true => Layout::horizontal([Constraint::Percentage(50); 2]).areas(body),
+            false => Layout::vertical([Constraint::Percentage(50); 2]).areas(body),
+        };
+
+        let prompt = "> ";
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled(prompt, self.fg(Color::Blue)),
+                Span::raw(&self.query),
+                Span::styled(
+                    format!("  {}/{}", self.matches.len(), self.entries.len()),
+                    self.fg(Color::DarkGray),
+                ),
+            ]),
+            search,
+        );
+        frame.set_cursor_position(Position::new(
+            search.x + (prompt.len() + self.query.chars().count()) as u16,
+            search.y,
+        ));
+
+        let (mark, hash, cached, dim) = (
+            self.fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            self.fg(Color::Yellow),
+            self.fg(Color::Green),
+            self.fg(Color::DarkGray),
+        );
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .map(|&i| {
+                let entry = &self.entries[i];
+                ListItem::new(Line::from(vec![
+                    Span::styled(if self.marked.contains(&i) { "+ " } else { "  " }, mark),
+                    Span::styled(&entry.hash[..7], hash),
+                    Span::styled(
+                        if entry.summary.is_some() {
+                            " ● "
+                        } else {
+                            "   "
+                        },
+                        cached,
+                    ),
+                    Span::raw(&entry.subject),
+                    Span::styled(format!("  {}, {}", entry.author, entry.age), dim),
+                ]))
+            })
+            .collect();
+        let title = match self.marked.len() {
+            0 => " Commits ".to_string(),
+            marked => format!(" Commits ({marked} marked) "),
+        };
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list,
+            &mut self.list,
+        );
+
+        let content = match self.highlighted() {
+            Some(i) => self.preview(i).to_string(),
+            None => String::new(),
+        };
+        frame.render_widget(
+            Paragraph::new(content)
+                .block(Block::bordered().title(" Preview "))
+                .wrap(Wrap { trim: false })
+                .scroll((self.preview_scroll, 0)),
+            preview,
+        );
+
+        let help = match &self.status {
+            Some(status) => Line::styled(status.as_str(), self.fg(Color::Red)),
+            None => Line::styled(
+                "↑/↓ move · tab mark · enter explain · ctrl-r explain as range · \
+                 shift-↑/↓ scroll preview · esc quit",
+                self.fg(Color::DarkGray),
+            ),
+        };
+        frame.render_widget(help, keys);
+    }
+
+    /// The header, message and stat of the entry at `i`, followed by its
+    /// cached summary.
+    fn preview(&mut self, i: usize) -> &str {
+        if !self.previews.contains_key(&i) {
+            let preview = self
+                .render_preview(&self.entries[i])
+                .unwrap_or_else(|err| err.to_string());
+            self.previews.insert(i, preview);
+        }
+
+        &self.previews[&i]
+    }
+
+    fn render_preview(&self, entry: &Entry) -> Result<String, GitRepoError> {
+        let commit = self.repo.commit(&entry.hash)?;
+        let author = commit.author();
+        let mut preview = format!(
+            "commit {}\nAuthor: {} <{}>\nDate:   {}\n\n",
+            entry.hash,
+            author.name().unwrap_or_default(),
+            author.email().unwrap_or_default(),
+            git_repo::format_time(commit.time()),
+        );
+        for line in commit.message().unwrap_or_default().trim_end().lines() {
+            preview.push_str(&format!("    {line}\n"));
+        }
+
+        let stat = self.repo.commit_stat(&commit)?;
+        if !stat.is_empty() {
+            preview.push_str(&format!("\n{}\n", stat.trim_end()));
+        }
+
+        if let Some(summary) = &entry.summary {
+            preview.push_str(&format!("\n── Cached summary ──\n\n{}\n", summary.title));
+            if !summary.summary.is_empty() {
+                preview.push_str(&format!("\n{}\n", summary.summary));
+            }
+            if !summary.changes.is_empty() {
+                preview.push('\n');
+            }
+            for change in &summary.changes {
+                preview.push_str(&format!("- {change}\n"));
+            }
+        }
+
+        Ok(preview)
+    }
+
+    /// `color` as the foreground, unless colors are turned off.
+    fn fg(&self, color: Color) -> Style {
+        match self.color {
+            true => Style::default().fg(color),
+            false => Style::default(),
+        }
+    }
+}
+
+/// How long ago something happened `seconds` ago, like git's `%cr`.
+fn relative_age(seconds: i64) -> String {
+    const DAY: i64 = 24 * 60 * 60;
+
+    let (count, unit) = match seconds.max(0) {
+        seconds if seconds < 60 => return "just now".to_string(),
+        seconds if seconds < 60 * 60 => (seconds / 60, "minute"),
+        seconds if seconds < DAY => (seconds / (60 * 60), "hour"),
+        seconds if seconds < 14 * DAY => (seconds / DAY, "day"),
+        seconds if seconds < 60 * DAY => (seconds / (7 * DAY), "week"),
+        seconds if seconds < 365 * DAY => (seconds / (30 * DAY), "month"),
+        seconds => (seconds / (365 * DAY), "year"),
+    };
+
+    match count {
+        1 => format!("1 {unit} ago"),
+        count => format!("{count} {unit}s ago"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_repo::fixture::Fixture;
+
+    /// A repository with three commits, and a cached summary of the oldest.
+    fn picker(fixture: &Fixture) -> CommitPicker<'_> {
+        let oldest = fixture.commit("Add response cache", &[("cache.rs", Some(b"1"))]);
+        fixture.commit("Fix cache eviction", &[("cache.rs", Some(b"2"))]);
+        fixture.commit("Create a changelog entry", &[("CHANGELOG.md", Some(b"3"))]);
+
+        let summaries = HashMap::from([(
+            oldest.to_string(),
+            r#"{"title": "Cache responses", "summary": "", "changes": []}"#.to_string(),
+        )]);
+        CommitPicker::new(&fixture.repo, &summaries, false).unwrap()
+    }
+
+    fn search<'p>(picker: &'p mut CommitPicker, query: &str) -> Vec<&'p str> {
+        picker.query = query.to_string();
+        picker.search();
+        picker
+            .matches
+            .iter()
+            .map(|&i| picker.entries[i].subject.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lists_every_commit_newest_first_without_a_query() {
+        let fixture = Fixture::new();
+        let mut picker = picker(&fixture);
+
+        assert_eq!(
+            search(&mut picker, ""),
+            [
+                "Create a changelog entry",
+                "Fix cache eviction",
+                "Add response cache"
+            ]
+        );
+        assert_eq!(picker.highlighted(), Some(0));
+
+        let summary = picker.entries[2].summary.as_ref().unwrap();
+        assert_eq!(summary.title, "Cache responses");
+        assert!(picker.entries[0].summary.is_none());
+    }
+
+    #[test]
+    fn ranks_close_matches_above_scattered_ones() {
+        let fixture = Fixture::new();
+        let mut picker = picker(&fixture);
+
+        assert_eq!(
+            search(&mut picker, "cache"),
+            [
+                "Fix cache eviction",
+                "Add response cache",
+                "Create a changelog entry"
+            ]
+        );
+        assert_eq!(search(&mut picker, "fixcache"), ["Fix cache eviction"]);
+        assert!(search(&mut picker, "rebase").is_empty());
+        assert_eq!(picker.highlighted(), None);
+    }
+
+    #[test]
+    fn matches_hash_prefixes_first() {
+        let fixture = Fixture::new();
+        let mut picker = picker(&fixture);
+
+        let hash = picker.entries[2].hash[..7].to_string();
+        assert_eq!(search(&mut picker, &hash)[0], "Add response cache");
+    }
+
+    #[test]
+    fn describes_ages_like_git() {
+        const DAY: i64 = 24 * 60 * 60;
+
+        assert_eq!(relative_age(-90), "just now");
+        assert_eq!(relative_age(0), "just now");
+        assert_eq!(relative_age(59), "just now");
+        assert_eq!(relative_age(60), "1 minute ago");
+        assert_eq!(relative_age(60 * 60 - 1), "59 minutes ago");
+        assert_eq!(relative_age(60 * 60), "1 hour ago");
+        assert_eq!(relative_age(DAY - 1), "23 hours ago");
+        assert_eq!(relative_age(DAY), "1 day ago");
+        assert_eq!(relative_age(14 * DAY - 1), "13 days ago");
+        assert_eq!(relative_age(14 * DAY), "2 weeks ago");
+        assert_eq!(relative_age(60 * DAY), "2 months ago");
+        assert_eq!(relative_age(365 * DAY - 1), "12 months ago");
+        assert_eq!(relative_age(365 * DAY), "1 year ago");
+        assert_eq!(relative_age(3 * 365 * DAY), "3 years ago");
+    }
+}
@@ -0,0 +1,865 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::git_repo::GitRepo;
+use crate::{Cli, ColorChoice, CommitFormat, OutputFormat, ProviderType, SecretPolicy};
+
+const GLOBAL_CONFIG_FILE: &str = "lumen/config.toml";
+const REPO_CONFIG_FILE: &str = ".lumen.toml";
+
+/// Keys that are only read from the global config, even in trusted
+/// repositories: the auth and header settings reach every request.
+const GLOBAL_KEYS: [&str; 4] = ["trusted_repos", "auth_header", "auth_scheme", "headers"];
+
+/// Keys read from `.lumen.toml` in repositories that are not listed in
+/// `trusted_repos`. The others decide where diffs and API keys are sent,
+/// what is redacted from diffs and which files are read, which a cloned
+/// repository must not control.
+const UNTRUSTED_REPO_KEYS: [&str; 8] = [
+    "format", "output", "color", "pager", "include", "exclude", "pr_base", "prompts",
+];
+
+#[derive(Debug, Clone)]
+pub enum ConfigError {
+    ReadError(PathBuf, String),
+    ParseError(PathBuf, String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::ReadError(path, err) => {
+                write!(f, "Could not read config file '{}': {err}", path.display())
+            }
+            ConfigError::ParseError(path, err) => {
+                write!(f, "Invalid config file '{}': {err}", path.display())
+            }
+        }
+    }
+}
+
+/// The contents of a single `config.toml` / `.lumen.toml` file.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    provider: Option<ProviderType>,
+    api_key: Option<String>,
+    model: Option<String>,
+    base_url: Option<String>,
+    auth_header: Option<String>,
+    auth_scheme: Option<String>,
+    format: Option<CommitFormat>,
+    output: Option<OutputFormat>,
+    color: Option<ColorChoice>,
+    pager: Option<bool>,
+    max_tokens: Option<u32>,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    pr_base: Option<String>,
+    pr_template: Option<PathBuf>,
+    retries: Option<u32>,
+    timeout_secs: Option<u64>,
+    cache: Option<bool>,
+    cache_ttl_days: Option<u64>,
+    cache_max_size_mb: Option<u64>,
+    secrets: Option<SecretPolicy>,
+    secret_patterns: Option<Vec<String>>,
+    fallback: Option<Vec<ProviderType>>,
+    trusted_repos: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    providers: BTreeMap<String, ProviderConfig>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    context_windows: BTreeMap<String, u32>,
+    #[serde(default)]
+    prompts: BTreeMap<String, PromptConfig>,
+}
+
+/// Replaces the system and/or user prompt template of one kind of request,
+/// e.g. `[prompts.commit]`. See `prompt_template` for the names and
+/// variables.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct PromptConfig {
+    system: Option<String>,
+    user: Option<String>,
+}
+
+/// Settings for a provider in the `fallback` list, e.g. `[providers.groq]`.
+/// The top-level `api_key`, `model` and `base_url` only apply to `provider`.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ProviderConfig {
+    api_key: Option<String>,
+    model: Option<String>,
+    base_url: Option<String>,
+}
+
+/// Where a config file was found, which decides the keys read from it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileScope {
+    Global,
+    Repo { trusted: bool },
+}
+
+#[derive(Clone, Debug)]
+pub enum ConfigSource {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Flag,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Env(name) => write!(f, "env {name}"),
+            ConfigSource::Flag => write!(f, "command line"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T) -> Self {
+        Setting {
+            value,
+            source: ConfigSource::Default,
+        }
+    }
+
+    fn set(&mut self, value: Option<T>, source: &ConfigSource) {
+        if let Some(value) = value {
+            self.value = value;
+            self.source = source.clone();
+        }
+    }
+}
+
+/// The effective configuration. Each value comes from the first of these that
+/// sets it: command line flags, environment variables, `.lumen.toml` at the
+/// repository root, `$XDG_CONFIG_HOME/lumen/config.toml`, built-in defaults.
+/// `.lumen.toml` is only read in full for repositories in `trusted_repos`.
+#[derive(Clone, Debug)]
+pub struct LumenConfig {
+    pub provider: Setting<ProviderType>,
+    pub api_key: Setting<Option<String>>,
+    pub model: Setting<Option<String>>,
+    pub base_url: Setting<Option<String>>,
+    pub auth_header: Setting<String>,
+    pub auth_scheme: Setting<String>,
+    pub headers: BTreeMap<String, Setting<String>>,
+    pub format: Setting<CommitFormat>,
+    pub output: Setting<OutputFormat>,
+    pub color: Setting<ColorChoice>,
+    pub pager: Setting<bool>,
+    pub max_tokens: Setting<Option<u32>>,
+    pub include: Setting<Vec<String>>,
+    pub exclude: Setting<Vec<String>>,
+    pub pr_base: Setting<Option<String>>,
+    pub pr_template: Setting<Option<PathBuf>>,
+    pub retries: Setting<u32>,
+    pub timeout_secs: Setting<u64>,
+    pub cache: Setting<bool>,
+    pub cache_ttl_days: Setting<u64>,
+    pub cache_max_size_mb: Setting<u64>,
+    pub secrets: Setting<SecretPolicy>,
+    pub secret_patterns: Setting<Vec<String>>,
+    pub fallback: Setting<Vec<ProviderType>>,
+    pub trusted_repos: Setting<Vec<PathBuf>>,
+    pub providers: BTreeMap<String, Setting<String>>,
+    pub context_windows: BTreeMap<String, Setting<u32>>,
+    pub prompts: BTreeMap<String, Setting<String>>,
+}
+
+impl LumenConfig {
+    pub fn load(cli: &Cli, matches: &ArgMatches) -> Result<Self, ConfigError> {
+        let mut config = Self::defaults();
+        config.merge_files(Self::global_path(), Self::repo_root())?;
+        config.merge_args(cli, matches);
+        Ok(config)
+    }
+
+    /// The built-in defaults, before any config file, variable or flag.
+    pub fn defaults() -> Self {
+        LumenConfig {
+            provider: Setting::new(ProviderType::Phind),
+            api_key: Setting::new(None),
+            model: Setting::new(None),
+            base_url: Setting::new(None),
+            auth_header: Setting::new("Authorization".to_string()),
+            auth_scheme: Setting::new("Bearer".to_string()),
+            headers: BTreeMap::new(),
+            format: Setting::new(CommitFormat::Plain),
+            output: Setting::new(OutputFormat::Text),
+            color: Setting::new(ColorChoice::Auto),
+            pager: Setting::new(false),
+            max_tokens: Setting::new(None),
+            include: Setting::new(Vec::new()),
+            exclude: Setting::new(Vec::new()),
+            pr_base: Setting::new(None),
+            pr_template: Setting::new(None),
+            retries: Setting::new(2),
+            timeout_secs: Setting::new(300),
+            cache: Setting::new(true),
+            cache_ttl_days: Setting::new(30),
+            cache_max_size_mb: Setting::new(50),
+            secrets: Setting::new(SecretPolicy::Redact),
+            secret_patterns: Setting::new(Vec::new()),
+            fallback: Setting::new(Vec::new()),
+            trusted_repos: Setting::new(Vec::new()),
+            providers: BTreeMap::new(),
+            context_windows: BTreeMap::new(),
+            prompts: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the global config at `global`, then `.lumen.toml` in the
+    /// repository at `repo_root`. Missing files are skipped.
+    fn merge_files(
+        &mut self,
+        global: Option<PathBuf>,
+        repo_root: Option<PathBuf>,
+    ) -> Result<(), ConfigError> {
+        if let Some(path) = global.filter(|path| path.exists()) {
+            self.merge_file(path, FileScope::Global)?;
+        }
+        if let Some(root) = repo_root {
+            let path = root.join(REPO_CONFIG_FILE);
+            if path.exists() {
+                let trusted = self.is_trusted(&root);
+                self.merge_file(path, FileScope::Repo { trusted })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the command line flags and environment variables.
+    fn merge_args(&mut self, cli: &Cli, matches: &ArgMatches) {
+        self.provider.set(
+            cli.provider,
+            &arg_source(matches, "provider", "LUMEN_AI_PROVIDER"),
+        );
+        self.api_key.set(
+            cli.api_key.clone().map(Some),
+            &arg_source(matches, "api_key", "LUMEN_API_KEY"),
+        );
+        self.model.set(
+            cli.model.clone().map(Some),
+            &arg_source(matches, "model", "LUMEN_AI_MODEL"),
+        );
+        self.base_url.set(
+            cli.base_url.clone().map(Some),
+            &arg_source(matches, "base_url", "LUMEN_BASE_URL"),
+        );
+        self.include.set(
+            (!cli.include.is_empty()).then(|| cli.include.clone()),
+            &ConfigSource::Flag,
+        );
+        self.exclude.set(
+            (!cli.exclude.is_empty()).then(|| cli.exclude.clone()),
+            &ConfigSource::Flag,
+        );
+        self.cache
+            .set(cli.no_cache.then_some(false), &ConfigSource::Flag);
+        self.fallback.set(
+            (!cli.fallback.is_empty()).then(|| cli.fallback.clone()),
+            &arg_source(matches, "fallback", "LUMEN_FALLBACK"),
+        );
+        self.output
+            .set(cli.output, &arg_source(matches, "output", "LUMEN_OUTPUT"));
+        self.color
+            .set(cli.color, &arg_source(matches, "color", "LUMEN_COLOR"));
+        self.pager.set(
+            match (cli.pager, cli.no_pager) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            &ConfigSource::Flag,
+        );
+        self.secrets.set(
+            cli.secrets,
+            &arg_source(matches, "secrets", "LUMEN_SECRETS"),
+        );
+    }
+
+    /// `$XDG_CONFIG_HOME/lumen/config.toml`, falling back to `~/.config`.
+    pub fn global_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_home.join(GLOBAL_CONFIG_FILE))
+    }
+
+    /// The top-level directory of the current repository, if any.
+    pub fn repo_root() -> Option<PathBuf> {
+        Some(GitRepo::open().ok()?.root()?.to_path_buf())
+    }
+
+    /// Whether the repository at `root` is listed in `trusted_repos`, so
+    /// that its `.lumen.toml` is read in full.
+    fn is_trusted(&self, root: &Path) -> bool {
+        let Ok(root) = fs::canonicalize(root) else {
+            return false;
+        };
+
+        self.trusted_repos
+            .value
+            .iter()
+            .any(|repo| fs::canonicalize(repo).is_ok_and(|repo| repo == root))
+    }
+
+    /// A user-configured prompt, e.g. `prompt("commit", "system")`.
+    pub fn prompt(&self, name: &str, part: &str) -> Option<&str> {
+        self.prompts
+            .get(&format!("{name}.{part}"))
+            .map(|setting| setting.value.as_str())
+    }
+
+    /// The configuration used for `provider` when it is a fallback: its
+    /// `[providers.<name>]` settings replace the top-level ones, and the
+    /// chat completions auth settings and headers are back to their defaults.
+    pub fn for_fallback(&self, provider: ProviderType) -> LumenConfig {
+        let name = value_name(provider);
+        let setting = |key: &str| match self.providers.get(&format!("{name}.{key}")) {
+            Some(setting) => Setting {
+                value: Some(setting.value.clone()),
+                source: setting.source.clone(),
+            },
+            None => Setting::new(None),
+        };
+
+        LumenConfig {
+            provider: Setting::new(provider),
+            api_key: setting("api_key"),
+            model: setting("model"),
+            base_url: setting("base_url"),
+            auth_header: Setting::new("Authorization".to_string()),
+            auth_scheme: Setting::new("Bearer".to_string()),
+            headers: BTreeMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// The configured context window of `model` in tokens, e.g.
+    /// `[context_windows] "gpt-4o" = 128000`.
+    pub fn context_window(&self, model: &str) -> Option<u32> {
+        self.context_windows.get(model).map(|setting| setting.value)
+    }
+
+    fn merge_file(&mut self, path: PathBuf, scope: FileScope) -> Result<(), ConfigError> {
+        let content = fs::read_to_string(&path)
+            .map_err(|err| ConfigError::ReadError(path.clone(), err.to_string()))?;
+        let parse_error =
+            |err: toml::de::Error| ConfigError::ParseError(path.clone(), err.to_string());
+        let mut table: toml::Table = toml::from_str(&content).map_err(parse_error)?;
+        let mut file =
+            ConfigFile::deserialize(toml::Value::Table(table.clone())).map_err(parse_error)?;
+
+        // Ignored keys are not an error, so that lumen keeps working in a
+        // cloned repository that ships its own config
+        if let FileScope::Repo { trusted } = scope {
+            let global_only = |key: &str| GLOBAL_KEYS.contains(&key);
+            let always_read = |key: &str| UNTRUSTED_REPO_KEYS.contains(&key);
+            let (global, untrusted): (Vec<String>, Vec<String>) = table
+                .keys()
+                .filter(|key| !always_read(key))
+                .cloned()
+                .partition(|key| global_only(key));
+
+            if !global.is_empty() {
+                eprintln!(
+                    "Warning: ignoring {} in '{}'; only the global config can set them",
+                    global.join(", "),
+                    path.display()
+                );
+            }
+            if !trusted && !untrusted.is_empty() {
+                eprintln!(
+                    "Warning: ignoring {} in '{}'; add the repository to `trusted_repos` in the \
+                     global config to use them",
+                    untrusted.join(", "),
+                    path.display()
+                );
+            }
+
+            table.retain(|key, _| always_read(key) || (trusted && !global_only(key)));
+            file = ConfigFile::deserialize(toml::Value::Table(table)).map_err(parse_error)?;
+        }
+
+        let source = ConfigSource::File(path);
+
+        self.provider.set(file.provider, &source);
+        self.api_key.set(file.api_key.map(Some), &source);
+        self.model.set(file.model.map(Some), &source);
+        self.base_url.set(file.base_url.map(Some), &source);
+        self.auth_header.set(file.auth_header, &source);
+        self.auth_scheme.set(file.auth_scheme, &source);
+
+        for (name, value) in file.headers {
+            self.headers.insert(
+                name,
+                Setting {
+                    value,
+                    source: source.clone(),
+                },
+            );
+        }
+        self.format.set(file.format, &source);
+        self.output.set(file.output, &source);
+        self.color.set(file.color, &source);
+        self.pager.set(file.pager, &source);
+        self.max_tokens.set(file.max_tokens.map(Some), &source);
+        self.include.set(file.include, &source);
+        self.exclude.set(file.exclude, &source);
+        self.pr_base.set(file.pr_base.map(Some), &source);
+        self.pr_template.set(file.pr_template.map(Some), &source);
+        self.retries.set(file.retries, &source);
+        self.timeout_secs.set(file.timeout_secs, &source);
+        self.cache.set(file.cache, &source);
+        self.cache_ttl_days.set(file.cache_ttl_days, &source);
+        self.cache_max_size_mb.set(file.cache_max_size_mb, &source);
+        self.secrets.set(file.secrets, &source);
+        self.secret_patterns.set(file.secret_patterns, &source);
+        self.fallback.set(file.fallback, &source);
+        self.trusted_repos.set(file.trusted_repos, &source);
+
+        for (provider, settings) in file.providers {
+            for (key, value) in [
+                ("api_key", settings.api_key),
+                ("model", settings.model),
+                ("base_url", settings.base_url),
+            ] {
+                if let Some(value) = value {
+                    self.providers.insert(
+                        format!("{provider}.{key}"),
+                        Setting {
+                            value,
+                            source: source.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        for (model, tokens) in file.context_windows {
+            self.context_windows.insert(
+                model,
+                Setting {
+                    value: tokens,
+                    source: source.clone(),
+                },
+            );
+        }
+
+        for (name, prompt) in file.prompts {
+            for (part, value) in [("system", prompt.system), ("user", prompt.user)] {
+                if let Some(value) = value {
+                    self.prompts.insert(
+                        format!("{name}.{part}"),
+                        Setting {
+                            value,
+                            source: source.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders the effective configuration as TOML, annotating every value
+    /// with where it came from. API keys are redacted.
+    pub fn show(&self) -> String {
+        let include = self.include.value.iter().map(quote).collect::<Vec<_>>();
+        let exclude = self.exclude.value.iter().map(quote).collect::<Vec<_>>();
+        let secret_patterns = self
+            .secret_patterns
+            .value
+            .iter()
+            .map(quote)
+            .collect::<Vec<_>>();
+        let fallback = self
+            .fallback
+            .value
+            .iter()
+            .map(|provider| quote(value_name(*provider)))
+            .collect::<Vec<_>>();
+        let trusted_repos = self
+            .trusted_repos
+            .value
+            .iter()
+            .map(|repo| quote(repo.display()))
+            .collect::<Vec<_>>();
+
+        let mut lines = vec![
+            show_line(
+                "provider",
+                Some(quote(value_name(self.provider.value))),
+                &self.provider.source,
+            ),
+            show_line(
+                "api_key",
+                self.api_key.value.as_deref().map(|key| quote(redact(key))),
+                &self.api_key.source,
+            ),
+            show_line(
+                "model",
+                self.model.value.as_ref().map(quote),
+                &self.model.source,
+            ),
+            show_line(
+                "base_url",
+                self.base_url.value.as_ref().map(quote),
+                &self.base_url.source,
+            ),
+            show_line(
+                "auth_header",
+                Some(quote(&self.auth_header.value)),
+                &self.auth_header.source,
+            ),
+            show_line(
+                "auth_scheme",
+                Some(quote(&self.auth_scheme.value)),
+                &self.auth_scheme.source,
+            ),
+            show_line(
+                "format",
+                Some(quote(value_name(self.format.value))),
+                &self.format.source,
+            ),
+            show_line(
+                "output",
+                Some(quote(value_name(self.output.value))),
+                &self.output.source,
+            ),
+            show_line(
+                "color",
+                Some(quote(value_name(self.color.value))),
+                &self.color.source,
+            ),
+            show_line(
+                "pager",
+                Some(self.pager.value.to_string()),
+                &self.pager.source,
+            ),
+            show_line(
+                "max_tokens",
+                self.max_tokens.value.map(|n| n.to_string()),
+                &self.max_tokens.source,
+            ),
+            show_line(
+                "include",
+                Some(format!("[{}]", include.join(", "))),
+                &self.include.source,
+            ),
+            show_line(
+                "exclude",
+                Some(format!("[{}]", exclude.join(", "))),
+                &self.exclude.source,
+            ),
+            show_line(
+                "pr_base",
+                self.pr_base.value.as_ref().map(quote),
+                &self.pr_base.source,
+            ),
+            show_line(
+                "pr_template",
+                self.pr_template
+                    .value
+                    .as_ref()
+                    .map(|path| quote(path.display())),
+                &self.pr_template.source,
+            ),
+            show_line(
+                "retries",
+                Some(self.retries.value.to_string()),
+                &self.retries.source,
+            ),
+            show_line(
+                "timeout_secs",
+                Some(self.timeout_secs.value.to_string()),
+                &self.timeout_secs.source,
+            ),
+            show_line(
+                "cache",
+                Some(self.cache.value.to_string()),
+                &self.cache.source,
+            ),
+            show_line(
+                "cache_ttl_days",
+                Some(self.cache_ttl_days.value.to_string()),
+                &self.cache_ttl_days.source,
+            ),
+            show_line(
+                "cache_max_size_mb",
+                Some(self.cache_max_size_mb.value.to_string()),
+                &self.cache_max_size_mb.source,
+            ),
+            show_line(
+                "fallback",
+                Some(format!("[{}]", fallback.join(", "))),
+                &self.fallback.source,
+            ),
+            show_line(
+                "trusted_repos",
+                Some(format!("[{}]", trusted_repos.join(", "))),
+                &self.trusted_repos.source,
+            ),
+            show_line(
+                "secrets",
+                Some(quote(value_name(self.secrets.value))),
+                &self.secrets.source,
+            ),
+            show_line(
+                "secret_patterns",
+                Some(format!("[{}]", secret_patterns.join(", "))),
+                &self.secret_patterns.source,
+            ),
+        ];
+
+        for (key, value) in &self.providers {
+            let shown = if key.ends_with(".api_key") {
+                redact(&value.value)
+            } else {
+                value.value.clone()
+            };
+            lines.push(show_line(
+                &format!("providers.{key}"),
+                Some(quote(shown)),
+                &value.source,
+            ));
+        }
+
+        // Extra headers often carry gateway credentials
+        for (name, value) in &self.headers {
+            lines.push(show_line(
+                &format!("headers.{}", quote(name)),
+                Some(quote(redact(&value.value))),
+                &value.source,
+            ));
+        }
+
+        for (model, tokens) in &self.context_windows {
+            lines.push(show_line(
+                &format!("context_windows.{}", quote(model)),
+                Some(tokens.value.to_string()),
+                &tokens.source,
+            ));
+        }
+
+        for (key, prompt) in &self.prompts {
+            lines.push(show_line(
+                &format!("prompts.{key}"),
+                Some(quote(&prompt.value)),
+                &prompt.source,
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Where clap took a value from. Only meaningful when the value is set.
+fn arg_source(matches: &ArgMatches, id: &str, env: &'static str) -> ConfigSource {
+    match matches.value_source(id) {
+        Some(ValueSource::EnvVariable) => ConfigSource::Env(env),
+        Some(ValueSource::CommandLine) => ConfigSource::Flag,
+        _ => ConfigSource::Default,
+    }
+}
+
+pub fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn show_line(key: &str, value: Option<String>, source: &ConfigSource) -> String {
+    match value {
+        Some(value) => format!("{key} = {value}  # {source}"),
+        None => format!("# {key} is not set"),
+    }
+}
+
+/// `value` as a single-line TOML basic string, escaping quotes, backslashes
+/// and control characters such as newlines.
+fn quote(value: impl Display) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.to_string().chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Keys shorter than this are hidden completely, as their first characters
+/// would give away too much of them.
+const REDACT_MIN_LENGTH: usize = 12;
+
+/// The first characters of `key`, enough to tell keys apart.
+pub fn redact(key: &str) -> String {
+    if key.chars().count() < REDACT_MIN_LENGTH {
+        return "...".to_string();
+    }
+
+    let visible: String = key.chars().take(4).collect();
+    format!("{visible}...")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    /// A global config and a repository with a `.lumen.toml`, trusted or
+    /// not, loaded with the given command line.
+    fn load(
+        global: &str,
+        repo: &str,
+        trusted: bool,
+        args: &[&str],
+    ) -> (LumenConfig, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join(REPO_CONFIG_FILE), repo).unwrap();
+
+        let trusted_repos = match trusted {
+            true => format!("trusted_repos = [{}]\n", quote(root.display())),
+            false => String::new(),
+        };
+        let global_path = dir.path().join("config.toml");
+        fs::write(&global_path, format!("{trusted_repos}{global}")).unwrap();
+
+        let matches = Cli::command()
+            .try_get_matches_from(["lumen"].iter().chain(args).chain(&["config", "show"]))
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+
+        let mut config = LumenConfig::defaults();
+        config
+            .merge_files(Some(global_path), Some(root.clone()))
+            .unwrap();
+        config.merge_args(&cli, &matches);
+        (config, dir)
+    }
+
+    fn source(config: &LumenConfig, key: &str) -> String {
+        let show = config.show();
+        let line = show
+            .lines()
+            .find(|line| line.starts_with(&format!("{key} = ")))
+            .unwrap_or_else(|| panic!("{key} not in\n{show}"));
+        line.rsplit_once("  # ").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn trusted_repo_overrides_global_except_global_keys() {
+        let (config, dir) = load(
+            "base_url = \"https://global\"\nauth_header = \"api-key\"\n",
+            "base_url = \"https://repo\"\nauth_header = \"X-Evil\"\n\
+             [headers]\nX-Forward = \"yes\"\n",
+            true,
+            &[],
+        );
+
+        assert_eq!(config.base_url.value.as_deref(), Some("https://repo"));
+        assert_eq!(config.auth_header.value, "api-key");
+        assert!(config.headers.is_empty());
+        assert_eq!(
+            source(&config, "auth_header"),
+            dir.path().join("config.toml").display().to_string()
+        );
+    }
+
+    #[test]
+    fn untrusted_repo_only_sets_harmless_keys() {
+        let (config, dir) = load(
+            "",
+            "base_url = \"https://evil\"\nauth_header = \"X-Evil\"\nformat = \"conventional\"\n\
+             [headers]\nX-Forward = \"yes\"\n",
+            false,
+            &[],
+        );
+
+        assert_eq!(config.base_url.value, None);
+        assert_eq!(config.auth_header.value, "Authorization");
+        assert!(config.headers.is_empty());
+        assert_eq!(config.format.value, CommitFormat::Conventional);
+        let repo_file = dir.path().join("repo").join(REPO_CONFIG_FILE);
+        assert_eq!(source(&config, "format"), repo_file.display().to_string());
+        assert_eq!(source(&config, "auth_header"), "default");
+    }
+
+    #[test]
+    fn flags_override_files() {
+        let (config, _dir) = load(
+            "model = \"global-model\"\noutput = \"json\"\n",
+            "output = \"text\"\n",
+            true,
+            &["--model", "flag-model"],
+        );
+
+        assert_eq!(config.model.value.as_deref(), Some("flag-model"));
+        assert_eq!(source(&config, "model"), "command line");
+        assert_eq!(config.output.value, OutputFormat::Text);
+        assert!(source(&config, "output").ends_with(REPO_CONFIG_FILE));
+        assert_eq!(source(&config, "retries"), "default");
+    }
+
+    #[test]
+    fn env_overrides_files() {
+        // No other test reads the provider, as the variable is process-wide
+        std::env::set_var("LUMEN_AI_PROVIDER", "ollama");
+        let (config, _dir) = load("provider = \"groq\"\n", "", true, &[]);
+        std::env::remove_var("LUMEN_AI_PROVIDER");
+
+        assert_eq!(config.provider.value, ProviderType::Ollama);
+        assert_eq!(source(&config, "provider"), "env LUMEN_AI_PROVIDER");
+    }
+
+    #[test]
+    fn quotes_as_toml() {
+        let value = "say \"hi\"\nC:\\path\t\u{7}";
+        let quoted = quote(value);
+        assert_eq!(quoted, r#""say \"hi\"\nC:\\path\t\u0007""#);
+
+        let parsed: toml::Table = toml::from_str(&format!("key = {quoted}")).unwrap();
+        assert_eq!(parsed["key"].as_str(), Some(value));
+    }
+
+    #[test]
+    fn redacts_short_keys_completely() {
+        assert_eq!(redact("abcd"), "...");
+        assert_eq!(redact("sk-12345678"), "...");
+        assert_eq!(redact("sk-proj-1234567890"), "sk-p...");
+    }
+}
@@ -0,0 +1,277 @@
+/// Commit types accepted in the header, following the Angular convention
+/// that most release tooling understands.
+pub const TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+#[derive(Debug, Clone)]
+pub enum ConventionalCommitError {
+    EmptyMessage,
+    InvalidHeader(String),
+    UnknownType(String),
+    EmptyScope(String),
+    EmptyDescription(String),
+    MissingBlankLine,
+    EmptyBreakingChange,
+}
+
+impl std::fmt::Display for ConventionalCommitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConventionalCommitError::EmptyMessage => write!(f, "commit message is empty"),
+            ConventionalCommitError::InvalidHeader(header) => write!(
+                f,
+                "header '{header}' does not match 'type(scope)!: description'"
+            ),
+            ConventionalCommitError::UnknownType(commit_type) => write!(
+                f,
+                "unknown commit type '{commit_type}' (expected one of {})",
+                TYPES.join(", ")
+            ),
+            ConventionalCommitError::EmptyScope(header) => {
+                write!(f, "header '{header}' has an empty scope")
+            }
+            ConventionalCommitError::EmptyDescription(header) => {
+                write!(f, "header '{header}' has an empty description")
+            }
+            ConventionalCommitError::MissingBlankLine => {
+                write!(f, "header must be followed by a blank line")
+            }
+            ConventionalCommitError::EmptyBreakingChange => {
+                write!(f, "BREAKING CHANGE footer has no description")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Footer {
+    pub token: String,
+    pub value: String,
+}
+
+/// A commit message parsed according to the Conventional Commits 1.0.0
+/// grammar: `type(scope)!: description`, an optional body and optional
+/// `token: value` footers.
+#[derive(Clone, Debug)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    pub fn parse(message: &str) -> Result<Self, ConventionalCommitError> {
+        let mut lines = message.trim().lines();
+        let header = lines
+            .next()
+            .filter(|header| !header.trim().is_empty())
+            .ok_or(ConventionalCommitError::EmptyMessage)?;
+        let mut commit = parse_header(header)?;
+
+        let rest: Vec<&str> = lines.collect();
+        if rest.first().is_some_and(|line| !line.trim().is_empty()) {
+            return Err(ConventionalCommitError::MissingBlankLine);
+        }
+
+        // Footers are the trailing paragraph if it starts with a footer token
+        let rest = rest.join("\n");
+        let footers = rest
+            .split("\n\n")
+            .map(|paragraph| paragraph.trim_matches('\n'))
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .last()
+            .filter(|last| parse_footer_line(last.lines().next().unwrap_or("")).is_some())
+            .map(parse_footers)
+            .unwrap_or_default();
+
+        for footer in footers
+            .iter()
+            .filter(|footer| is_breaking_token(&footer.token))
+        {
+            if footer.value.trim().is_empty() {
+                return Err(ConventionalCommitError::EmptyBreakingChange);
+            }
+            commit.breaking = true;
+        }
+
+        Ok(commit)
+    }
+}
+
+fn parse_header(header: &str) -> Result<ConventionalCommit, ConventionalCommitError> {
+    let invalid = || ConventionalCommitError::InvalidHeader(header.to_string());
+
+    let (prefix, description) = header.split_once(": ").ok_or_else(invalid)?;
+    let description = description.trim();
+    if description.is_empty() {
+        return Err(ConventionalCommitError::EmptyDescription(
+            header.to_string(),
+        ));
+    }
+
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+
+    let (commit_type, scope) = match prefix.split_once('(') {
+        Some((commit_type, scope)) => {
+            let scope = scope.strip_suffix(')').ok_or_else(invalid)?;
+            if scope.contains(['(', ')']) {
+                return Err(invalid());
+            }
+            if scope.trim().is_empty() {
+                return Err(ConventionalCommitError::EmptyScope(header.to_string()));
+            }
+            (commit_type, Some(scope.to_string()))
+        }
+        None => (prefix, None),
+    };
+
+    if commit_type.is_empty() || !commit_type.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+    if !TYPES.contains(&commit_type) {
+        return Err(ConventionalCommitError::UnknownType(
+            commit_type.to_string(),
+        ));
+    }
+
+    Ok(ConventionalCommit {
+        commit_type: commit_type.to_string(),
+        scope,
+        breaking,
+        description: description.to_string(),
+    })
+}
+
+fn parse_footers(paragraph: &str) -> Vec<Footer> {
+    let mut footers: Vec<Footer> = Vec::new();
+
+    for line in paragraph.lines() {
+        match (parse_footer_line(line), footers.last_mut()) {
+            (Some(footer), _) => footers.push(footer),
+            // Footer values may continue over several lines
+            (None, Some(footer)) => {
+                footer.value.push('\n');
+                footer.value.push_str(line);
+            }
+            (None, None) => {}
+        }
+    }
+
+    footers
+}
+
+/// Parses `token: value` and `token #value` footer lines. Tokens use `-` in
+/// place of whitespace, except for `BREAKING CHANGE`.
+pub fn parse_footer_line(line: &str) -> Option<Footer> {
+    let (token, value) = line.split_once(": ").or_else(|| line.split_once(" #"))?;
+
+    let valid_token = token == "BREAKING CHANGE"
+        || (!token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+
+    valid_token.then(|| Footer {
+        token: token.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn is_breaking_token(token: &str) -> bool {
+    token == "BREAKING CHANGE" || token == "BREAKING-CHANGE"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_type_and_description() {
+        let commit = ConventionalCommit::parse("fix: handle empty diffs").unwrap();
+
+        assert_eq!(commit.commit_type, "fix");
+        assert_eq!(commit.scope, None);
+        assert!(!commit.breaking);
+        assert_eq!(commit.description, "handle empty diffs");
+    }
+
+    #[test]
+    fn parses_scope() {
+        let commit = ConventionalCommit::parse("feat(cli): add --format").unwrap();
+
+        assert_eq!(commit.commit_type, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("cli"));
+        assert_eq!(commit.description, "add --format");
+    }
+
+    #[test]
+    fn rejects_empty_scope() {
+        assert!(matches!(
+            ConventionalCommit::parse("feat(): add --format"),
+            Err(ConventionalCommitError::EmptyScope(_))
+        ));
+    }
+
+    #[test]
+    fn exclamation_mark_marks_breaking_change() {
+        let commit = ConventionalCommit::parse("refactor(api)!: drop v1 endpoints").unwrap();
+
+        assert!(commit.breaking);
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+    }
+
+    #[test]
+    fn breaking_change_footer_marks_breaking_change() {
+        let message = "feat: new config format\n\n\
+                       Configs are read from TOML now.\n\n\
+                       BREAKING CHANGE: JSON configs are no longer read";
+        let commit = ConventionalCommit::parse(message).unwrap();
+
+        assert!(commit.breaking);
+    }
+
+    #[test]
+    fn breaking_change_in_body_is_not_a_footer() {
+        let message = "feat: new config format\n\n\
+                       BREAKING CHANGE: mentioned in the body\n\n\
+                       Refs: #12";
+        let commit = ConventionalCommit::parse(message).unwrap();
+
+        assert!(!commit.breaking);
+    }
+
+    #[test]
+    fn rejects_unknown_type() {
+        assert!(matches!(
+            ConventionalCommit::parse("feature: add --format"),
+            Err(ConventionalCommitError::UnknownType(commit_type)) if commit_type == "feature"
+        ));
+    }
+
+    #[test]
+    fn rejects_upper_case_type() {
+        // Release tooling matches the lower-case types
+        assert!(matches!(
+            ConventionalCommit::parse("FEAT: add --format"),
+            Err(ConventionalCommitError::UnknownType(commit_type)) if commit_type == "FEAT"
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_blank_line_before_body() {
+        assert!(matches!(
+            ConventionalCommit::parse("fix: handle empty diffs\nThe diff was unwrapped."),
+            Err(ConventionalCommitError::MissingBlankLine)
+        ));
+    }
+
+    #[test]
+    fn rejects_header_without_type() {
+        assert!(matches!(
+            ConventionalCommit::parse("handle empty diffs"),
+            Err(ConventionalCommitError::InvalidHeader(_))
+        ));
+    }
+}
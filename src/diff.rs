@@ -0,0 +1,333 @@
+/// Replaces the contents of a binary file in a diff sent to the provider.
+const BINARY_PLACEHOLDER: &str = "Binary file changed";
+
+/// The part of a unified diff that belongs to a single file, starting at its
+/// `diff --git` header.
+#[derive(Clone, Debug)]
+pub struct FileDiff {
+    pub path: String,
+    pub content: String,
+}
+
+/// Splits the output of `git diff` / `git diff-tree -p` into the text that
+/// precedes the first file (commit hash, stat summary) and one entry per file.
+pub fn split(diff: &str) -> (String, Vec<FileDiff>) {
+    let mut preamble = String::new();
+    let mut files: Vec<FileDiff> = Vec::new();
+
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git ") {
+            files.push(FileDiff {
+                path: String::new(),
+                content: String::new(),
+            });
+        }
+
+        match files.last_mut() {
+            Some(file) => file.content.push_str(line),
+            None => preamble.push_str(line),
+        }
+    }
+
+    for file in files.iter_mut() {
+        file.path = file_path(&file.content);
+    }
+
+    (preamble, files)
+}
+
+/// Reassembles a diff produced by [`split`].
+pub fn join(preamble: &str, files: &[FileDiff]) -> String {
+    let mut diff = preamble.to_string();
+    for file in files {
+        diff.push_str(&file.content);
+    }
+    diff
+}
+
+/// The paths of the files a diff changes.
+pub fn files(diff: &str) -> Vec<String> {
+    split(diff).1.into_iter().map(|file| file.path).collect()
+}
+
+/// Replaces the contents of binary file sections with a one-line
+/// placeholder, keeping the header lines that say whether the file was added,
+/// deleted or renamed.
+pub fn replace_binary(diff: &str) -> String {
+    let (preamble, mut files) = split(diff);
+
+    for file in files.iter_mut() {
+        let Some(position) = file.content.lines().position(|line| {
+            line.starts_with("GIT binary patch") || line.starts_with("Binary files ")
+        }) else {
+            continue;
+        };
+
+        let mut content: String = file.content.split_inclusive('\n').take(position).collect();
+        content.push_str(BINARY_PLACEHOLDER);
+        content.push('\n');
+        file.content = content;
+    }
+
+    join(&preamble, &files)
+}
+
+/// A rough token count for `text`. Tokenizers differ between models, but
+/// English text and source code average about four bytes per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Splits a diff into chunks of at most `max_tokens` each. Files are kept
+/// together where possible; larger files are split between hunks, and hunks
+/// that are still too large between lines. Every piece of a split file
+/// repeats the file's header so it can be understood on its own.
+pub fn chunk(diff: &str, max_tokens: usize) -> Vec<String> {
+    let (preamble, files) = split(diff);
+
+    let mut pieces = Vec::new();
+    if !preamble.trim().is_empty() {
+        pieces.extend(split_lines("", &preamble, max_tokens));
+    }
+    for file in &files {
+        if estimate_tokens(&file.content) <= max_tokens {
+            pieces.push(file.content.clone());
+            continue;
+        }
+
+        let (header, hunks) = split_hunks(&file.content);
+        for hunk in hunks {
+            if estimate_tokens(&header) + estimate_tokens(&hunk) <= max_tokens {
+                pieces.push(format!("{header}{hunk}"));
+            } else {
+                pieces.extend(split_lines(&header, &hunk, max_tokens));
+            }
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    for piece in pieces {
+        match chunks.last_mut() {
+            Some(chunk) if estimate_tokens(chunk) + estimate_tokens(&piece) <= max_tokens => {
+                chunk.push_str(&piece)
+            }
+            _ => chunks.push(piece),
+        }
+    }
+
+    chunks
+}
+
+/// Splits a file section into the lines before its first hunk and one entry
+/// per `@@` hunk.
+fn split_hunks(content: &str) -> (String, Vec<String>) {
+    let mut header = String::new();
+    let mut hunks: Vec<String> = Vec::new();
+
+    for line in content.split_inclusive('\n') {
+        if line.starts_with("@@") {
+            hunks.push(String::new());
+        }
+
+        match hunks.last_mut() {
+            Some(hunk) => hunk.push_str(line),
+            None => header.push_str(line),
+        }
+    }
+
+    (header, hunks)
+}
+
+/// Splits `text` between lines into pieces that fit in `max_tokens` together
+/// with `header`. Single lines longer than that are truncated.
+fn split_lines(header: &str, text: &str, max_tokens: usize) -> Vec<String> {
+    let max_len = max_tokens.saturating_sub(estimate_tokens(header)).max(1) * 4;
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for line in text.split_inclusive('\n') {
+        let line = if line.len() > max_len {
+            let mut end = max_len - 1;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}\n", &line[..end])
+        } else {
+            line.to_string()
+        };
+
+        if !current.is_empty() && current.len() + line.len() > max_len {
+            pieces.push(format!("{header}{current}"));
+            current.clear();
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        pieces.push(format!("{header}{current}"));
+    }
+
+    pieces
+}
+
+/// Finds the path of the file a diff section belongs to, preferring the
+/// post-image path so that renamed and added files are reported by their new
+/// name.
+fn file_path(content: &str) -> String {
+    for line in content.lines() {
+        if let Some(path) = line.strip_prefix("+++ b/") {
+            return path.to_string();
+        }
+        if let Some(path) = line.strip_prefix("rename to ") {
+            return path.to_string();
+        }
+        if line.starts_with("@@") {
+            break;
+        }
+    }
+
+    for line in content.lines() {
+        if let Some(path) = line.strip_prefix("--- a/") {
+            return path.to_string();
+        }
+        if line.starts_with("@@") {
+            break;
+        }
+    }
+
+    // Binary and mode-only changes have no `---`/`+++` lines. Both sides of
+    // the header name the same file, so the path is the second half.
+    let header = content.lines().next().unwrap_or_default();
+    let paths = header.strip_prefix("diff --git a/").unwrap_or(header);
+    let half = paths.len().saturating_sub(3) / 2;
+    paths.get(half + 3..).unwrap_or(paths).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A diff section adding `lines` numbered lines to `path`, in one hunk.
+    fn file_diff(path: &str, lines: usize) -> String {
+        let mut diff = format!(
+            "diff --git a/{path} b/{path}\n\
+             --- a/{path}\n\
+             +++ b/{path}\n\
+             @@ -0,0 +1,{lines} @@\n"
+        );
+        for line in 1..=lines {
+            diff.push_str(&format!("+line {line} of {path}\n"));
+        }
+        diff
+    }
+
+    #[test]
+    fn finds_file_paths() {
+        let diff = format!(
+            "{}diff --git a/old.rs b/new.rs\n\
+             similarity index 100%\n\
+             rename from old.rs\n\
+             rename to new.rs\n\
+             diff --git a/logo.png b/logo.png\n\
+             Binary files a/logo.png and b/logo.png differ\n",
+            file_diff("src/main.rs", 1)
+        );
+
+        assert_eq!(files(&diff), ["src/main.rs", "new.rs", "logo.png"]);
+    }
+
+    #[test]
+    fn small_diff_is_one_chunk() {
+        let diff = format!("{}{}", file_diff("a.rs", 3), file_diff("b.rs", 3));
+
+        assert_eq!(chunk(&diff, 1_000), [diff]);
+    }
+
+    #[test]
+    fn files_are_packed_into_chunks() {
+        let (a, b, c) = (
+            file_diff("a.rs", 20),
+            file_diff("b.rs", 20),
+            file_diff("c.rs", 20),
+        );
+        let budget = estimate_tokens(&a) * 2 + 1;
+        let diff = format!("{a}{b}{c}");
+
+        assert_eq!(chunk(&diff, budget), [format!("{a}{b}"), c]);
+    }
+
+    #[test]
+    fn file_larger_than_the_budget_is_split() {
+        let diff = file_diff("big.rs", 500);
+        let chunks = chunk(&diff, 200);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(estimate_tokens(chunk) <= 200);
+            // Every piece repeats the header, so it can be read on its own
+            assert!(chunk.starts_with("diff --git a/big.rs b/big.rs\n"));
+        }
+
+        let lines: Vec<&str> = chunks
+            .iter()
+            .flat_map(|chunk| chunk.lines())
+            .filter(|line| line.starts_with("+line"))
+            .collect();
+        assert_eq!(lines.len(), 500);
+        assert_eq!(lines[499], "+line 500 of big.rs");
+    }
+
+    #[test]
+    fn line_longer_than_the_budget_is_truncated() {
+        let diff = format!(
+            "diff --git a/min.js b/min.js\n@@ -0,0 +1 @@\n+{}\n",
+            "x".repeat(10_000)
+        );
+
+        for chunk in chunk(&diff, 100) {
+            assert!(estimate_tokens(&chunk) <= 100);
+        }
+    }
+
+    #[test]
+    fn replaces_binary_patches() {
+        let diff = format!(
+            "diff --git a/logo.png b/logo.png\n\
+             new file mode 100644\n\
+             index 0000000..1234567\n\
+             GIT binary patch\n\
+             literal 1024\n\
+             zcmV-aBmf6IP)<h;3K|Lk000e1NJLTq001BW001Bm1^@s6w8PbT00004XF*Lt0A2t9\n\
+             \n\
+             {}",
+            file_diff("src/main.rs", 2)
+        );
+
+        assert_eq!(
+            replace_binary(&diff),
+            format!(
+                "diff --git a/logo.png b/logo.png\n\
+                 new file mode 100644\n\
+                 index 0000000..1234567\n\
+                 {BINARY_PLACEHOLDER}\n\
+                 {}",
+                file_diff("src/main.rs", 2)
+            )
+        );
+    }
+
+    #[test]
+    fn replaces_binary_file_notices() {
+        let diff = "diff --git a/logo.png b/logo.png\n\
+                    index 1234567..89abcde 100644\n\
+                    Binary files a/logo.png and b/logo.png differ\n";
+
+        assert_eq!(
+            replace_binary(diff),
+            format!(
+                "diff --git a/logo.png b/logo.png\n\
+                 index 1234567..89abcde 100644\n\
+                 {BINARY_PLACEHOLDER}\n"
+            )
+        );
+    }
+}
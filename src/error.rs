@@ -1,11 +1,34 @@
 use std::io;
 
-use crate::{git_commit::GitCommitError, git_staged::GitStagedError};
+use crate::{
+    cache::CacheError, commit_picker::CommitPickerError, config::ConfigError,
+    conventional_commit::ConventionalCommitError, git_branch::GitBranchError,
+    git_commit::GitCommitError, git_hook::GitHookError, git_range::GitRangeError,
+    git_repo::GitRepoError, git_staged::GitStagedError, path_filter::PathFilterError,
+    prompt_template::PromptTemplateError, provider::error::ProviderError, secrets::SecretsError,
+};
 
 pub enum LumenError {
     GitCommitError(GitCommitError),
     GitStagedError(GitStagedError),
+    GitHookError(GitHookError),
+    GitRangeError(GitRangeError),
+    GitBranchError(GitBranchError),
+    GitRepoError(GitRepoError),
+    ConfigError(ConfigError),
+    CacheError(CacheError),
+    CommitPickerError(CommitPickerError),
+    PathFilterError(PathFilterError),
+    PromptTemplateError(PromptTemplateError),
+    SecretsError(SecretsError),
+    ProviderError(ProviderError),
     MissingApiKey(String),
+    MissingSetting(String, String),
+    InvalidHeader(String, String),
+    ModelListingNotSupported(String),
+    JsonOutputNotSupported(String),
+    InvalidConventionalCommit(ConventionalCommitError, String),
+    PrTemplateMismatch(Vec<String>, String),
     UnknownError(Box<dyn std::error::Error>),
 }
 
@@ -21,9 +44,89 @@ impl From<GitStagedError> for LumenError {
     }
 }
 
+impl From<GitHookError> for LumenError {
+    fn from(err: GitHookError) -> LumenError {
+        LumenError::GitHookError(err)
+    }
+}
+
+impl From<GitRangeError> for LumenError {
+    fn from(err: GitRangeError) -> LumenError {
+        LumenError::GitRangeError(err)
+    }
+}
+
+impl From<GitBranchError> for LumenError {
+    fn from(err: GitBranchError) -> LumenError {
+        LumenError::GitBranchError(err)
+    }
+}
+
+impl From<GitRepoError> for LumenError {
+    fn from(err: GitRepoError) -> LumenError {
+        LumenError::GitRepoError(err)
+    }
+}
+
+impl From<ConfigError> for LumenError {
+    fn from(err: ConfigError) -> LumenError {
+        LumenError::ConfigError(err)
+    }
+}
+
+impl From<PromptTemplateError> for LumenError {
+    fn from(err: PromptTemplateError) -> LumenError {
+        LumenError::PromptTemplateError(err)
+    }
+}
+
+impl From<SecretsError> for LumenError {
+    fn from(err: SecretsError) -> LumenError {
+        LumenError::SecretsError(err)
+    }
+}
+
+impl From<CacheError> for LumenError {
+    fn from(err: CacheError) -> LumenError {
+        LumenError::CacheError(err)
+    }
+}
+
+impl From<CommitPickerError> for LumenError {
+    fn from(err: CommitPickerError) -> LumenError {
+        LumenError::CommitPickerError(err)
+    }
+}
+
+impl From<PathFilterError> for LumenError {
+    fn from(err: PathFilterError) -> LumenError {
+        LumenError::PathFilterError(err)
+    }
+}
+
+impl From<ProviderError> for LumenError {
+    fn from(err: ProviderError) -> LumenError {
+        LumenError::ProviderError(err)
+    }
+}
+
+// Providers return boxed errors through the AIProvider trait; provider errors
+// are unboxed so that callers can tell them apart
 impl From<Box<dyn std::error::Error>> for LumenError {
     fn from(err: Box<dyn std::error::Error>) -> LumenError {
-        LumenError::UnknownError(err)
+        match err.downcast::<ProviderError>() {
+            Ok(err) => LumenError::ProviderError(*err),
+            Err(err) => LumenError::UnknownError(err),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for LumenError {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> LumenError {
+        match err.downcast::<ProviderError>() {
+            Ok(err) => LumenError::ProviderError(*err),
+            Err(err) => LumenError::UnknownError(err),
+        }
     }
 }
 
@@ -44,8 +147,38 @@ impl std::fmt::Display for LumenError {
         match self {
             LumenError::GitStagedError(err) => write!(f, "{err}"),
             LumenError::GitCommitError(err) => write!(f, "{err}"),
+            LumenError::GitHookError(err) => write!(f, "{err}"),
+            LumenError::GitRangeError(err) => write!(f, "{err}"),
+            LumenError::GitBranchError(err) => write!(f, "{err}"),
+            LumenError::GitRepoError(err) => write!(f, "{err}"),
+            LumenError::ConfigError(err) => write!(f, "{err}"),
+            LumenError::CacheError(err) => write!(f, "{err}"),
+            LumenError::CommitPickerError(err) => write!(f, "{err}"),
+            LumenError::PathFilterError(err) => write!(f, "{err}"),
+            LumenError::PromptTemplateError(err) => write!(f, "{err}"),
+            LumenError::SecretsError(err) => write!(f, "{err}"),
+            LumenError::ProviderError(err) => write!(f, "{err}"),
             LumenError::UnknownError(err) => write!(f, "{err}"),
             LumenError::MissingApiKey(provider) => write!(f, "Missing API key for {provider}"),
+            LumenError::MissingSetting(setting, provider) => {
+                write!(f, "Missing {setting} for {provider}")
+            }
+            LumenError::InvalidHeader(name, err) => write!(f, "Invalid header '{name}': {err}"),
+            LumenError::ModelListingNotSupported(provider) => {
+                write!(f, "Listing models is not supported for {provider}")
+            }
+            LumenError::JsonOutputNotSupported(command) => {
+                write!(f, "`{command}` does not support `--output json`")
+            }
+            LumenError::InvalidConventionalCommit(err, message) => write!(
+                f,
+                "Provider did not return a valid Conventional Commit ({err}):\n\n{message}"
+            ),
+            LumenError::PrTemplateMismatch(missing, description) => write!(
+                f,
+                "Provider did not follow the pull request template (missing {}):\n\n{description}",
+                missing.join(", ")
+            ),
         }
     }
 }
@@ -0,0 +1,66 @@
+use crate::git_repo::{GitRepo, GitRepoError};
+
+#[derive(Debug, Clone)]
+pub enum GitBranchError {
+    CommandError(String),
+    DetachedHead(),
+    NoBaseBranch(),
+}
+
+impl From<GitRepoError> for GitBranchError {
+    fn from(err: GitRepoError) -> GitBranchError {
+        GitBranchError::CommandError(err.to_string())
+    }
+}
+
+impl std::fmt::Display for GitBranchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitBranchError::CommandError(err) => write!(f, "{err}"),
+            GitBranchError::DetachedHead() => write!(f, "HEAD is not on a branch"),
+            GitBranchError::NoBaseBranch() => write!(
+                f,
+                "Could not determine the base branch (hint: pass --base or set pr_base in the config)"
+            ),
+        }
+    }
+}
+
+/// The current branch and the branch it will be merged into.
+#[derive(Clone, Debug)]
+pub struct GitBranch {
+    pub name: String,
+    pub base: String,
+}
+
+impl GitBranch {
+    pub fn current(repo: &GitRepo, base: Option<String>) -> Result<Self, GitBranchError> {
+        let name = Self::current_name(repo)?.ok_or(GitBranchError::DetachedHead())?;
+
+        let base = match base {
+            Some(base) => base,
+            None => Self::default_base(repo)?,
+        };
+
+        Ok(GitBranch { name, base })
+    }
+
+    /// The name of the checked out branch, or `None` when HEAD is detached.
+    pub fn current_name(repo: &GitRepo) -> Result<Option<String>, GitBranchError> {
+        Ok(repo.current_branch()?)
+    }
+
+    /// The branch `origin/HEAD` points to, falling back to a local `main` or
+    /// `master` branch.
+    fn default_base(repo: &GitRepo) -> Result<String, GitBranchError> {
+        if let Some(base) = repo.remote_default_branch() {
+            return Ok(base);
+        }
+
+        ["main", "master"]
+            .into_iter()
+            .find(|base| repo.has_branch(base))
+            .map(String::from)
+            .ok_or(GitBranchError::NoBaseBranch())
+    }
+}
@@ -1,23 +1,23 @@
-use std::io;
-use std::process::Command;
-use std::string::FromUtf8Error;
+use git2::Commit;
+
+use crate::diff;
+use crate::git_repo::{self, GitRepo, GitRepoError};
+use crate::path_filter::PathFilter;
 
 #[derive(Debug, Clone)]
 pub enum GitCommitError {
     CommandError(String),
     InvalidCommit(String),
     EmptyDiff(String),
+    AllExcluded(String, Vec<String>),
 }
 
-impl From<io::Error> for GitCommitError {
-    fn from(err: io::Error) -> GitCommitError {
-        GitCommitError::CommandError(err.to_string())
-    }
-}
-
-impl From<FromUtf8Error> for GitCommitError {
-    fn from(err: FromUtf8Error) -> GitCommitError {
-        GitCommitError::CommandError(err.to_string())
+impl From<GitRepoError> for GitCommitError {
+    fn from(err: GitRepoError) -> GitCommitError {
+        match err {
+            GitRepoError::InvalidRevision(sha) => GitCommitError::InvalidCommit(sha),
+            err => GitCommitError::CommandError(err.to_string()),
+        }
     }
 }
 
@@ -27,6 +27,11 @@ impl std::fmt::Display for GitCommitError {
             GitCommitError::CommandError(err) => write!(f, "{err}"),
             GitCommitError::InvalidCommit(sha) => write!(f, "Commit '{sha}' not found"),
             GitCommitError::EmptyDiff(sha) => write!(f, "Diff for commit '{sha}' is empty"),
+            GitCommitError::AllExcluded(sha, files) => write!(
+                f,
+                "All changes in commit '{sha}' are excluded by path filters ({})",
+                files.join(", ")
+            ),
         }
     }
 }
@@ -36,109 +41,63 @@ pub struct GitCommit {
     pub full_hash: String,
     pub message: String,
     pub diff: String,
+    pub files: Vec<String>,
+    pub omitted_files: Vec<String>,
     pub author_name: String,
     pub author_email: String,
     pub date: String,
 }
 
 impl GitCommit {
-    pub fn new(sha: String) -> Result<Self, GitCommitError> {
-        let _ = Self::is_valid_commit(&sha)?;
-
-        Ok(GitCommit {
-            full_hash: Self::get_full_hash(&sha)?,
-            message: Self::get_message(&sha)?,
-            diff: Self::get_diff(&sha)?,
-            author_name: Self::get_author_name(&sha)?,
-            author_email: Self::get_author_email(&sha)?,
-            date: Self::get_date(&sha)?,
-        })
-    }
-
-    pub fn is_valid_commit(sha: &str) -> Result<(), GitCommitError> {
-        let output = Command::new("git").args(["cat-file", "-t", sha]).output()?;
-        let output_str = String::from_utf8(output.stdout)?;
-
-        if output_str.trim() == "commit" {
-            return Ok(());
-        }
-
-        Err(GitCommitError::InvalidCommit(sha.to_string()))
-    }
-
-    fn get_full_hash(sha: &str) -> Result<String, GitCommitError> {
-        let output = Command::new("git").args(["rev-parse", sha]).output()?;
-
-        let mut full_hash = String::from_utf8(output.stdout)?;
-        full_hash.pop(); // Remove trailing newline
-        Ok(full_hash)
+    pub fn new(repo: &GitRepo, sha: String, filter: &PathFilter) -> Result<Self, GitCommitError> {
+        let commit = repo.commit(&sha)?;
+        Self::from_commit(repo, &commit, filter)
     }
 
-    fn get_diff(sha: &str) -> Result<String, GitCommitError> {
-        let output = Command::new("git")
-            .args([
-                "diff-tree",
-                "-p",
-                "--binary",
-                "--no-color",
-                "--compact-summary",
-                sha,
-            ])
-            .output()?;
-
-        let diff = String::from_utf8(output.stdout)?;
+    /// Reads the metadata and diff of a commit that is already resolved.
+    pub fn from_commit(
+        repo: &GitRepo,
+        commit: &Commit,
+        filter: &PathFilter,
+    ) -> Result<Self, GitCommitError> {
+        let diff = repo.commit_diff(commit, filter)?;
         if diff.is_empty() {
-            return Err(GitCommitError::EmptyDiff(sha.to_string()));
+            return Err(GitCommitError::EmptyDiff(commit.id().to_string()));
+        }
+        let (diff, omitted_files) = filter.apply(&diff);
+        let files = diff::files(&diff);
+        if files.is_empty() && !omitted_files.is_empty() {
+            return Err(GitCommitError::AllExcluded(
+                commit.id().to_string(),
+                omitted_files,
+            ));
         }
 
-        Ok(diff)
-    }
-
-    fn get_message(sha: &str) -> Result<String, GitCommitError> {
-        let output = Command::new("git")
-            .args(["log", "--format=%B", "-n", "1", sha])
-            .output()?;
-
-        let mut message = String::from_utf8(output.stdout)?;
-        message.pop(); // Remove trailing newline
-        message.pop();
-        Ok(message)
-    }
-
-    fn get_author_name(sha: &str) -> Result<String, GitCommitError> {
-        let output = Command::new("git")
-            .args(["log", "--format=%an", "-n", "1", sha])
-            .output()?;
-
-        let mut name = String::from_utf8(output.stdout)?;
-        name.pop(); // Remove trailing newline
-        Ok(name)
-    }
-
-    fn get_author_email(sha: &str) -> Result<String, GitCommitError> {
-        let output = Command::new("git")
-            .args(["log", "--format=%ae", "-n", "1", sha])
-            .output()?;
-
-        let mut email = String::from_utf8(output.stdout)?;
-        email.pop(); // Remove trailing newline
-        Ok(email)
+        Ok(GitCommit {
+            files,
+            diff: diff::replace_binary(&diff),
+            omitted_files,
+            ..Self::metadata(commit)
+        })
     }
 
-    fn get_date(sha: &str) -> Result<String, GitCommitError> {
-        let output = Command::new("git")
-            .args([
-                "log",
-                "--format=%cd",
-                "--date=format:%Y-%m-%d %H:%M:%S",
-                "-n",
-                "1",
-                sha,
-            ])
-            .output()?;
-
-        let mut date = String::from_utf8(output.stdout)?;
-        date.pop(); // Remove trailing newline
-        Ok(date)
+    /// Reads the hash, message, author and date of a commit without its
+    /// diff, e.g. for the commits of a range, whose changes are diffed as a
+    /// whole.
+    pub fn metadata(commit: &Commit) -> Self {
+        let author = commit.author();
+
+        GitCommit {
+            full_hash: commit.id().to_string(),
+            message: String::from_utf8_lossy(commit.message_bytes())
+                .trim_end()
+                .to_string(),
+            diff: String::new(),
+            files: Vec::new(),
+            omitted_files: Vec::new(),
+            author_name: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+            author_email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+            date: git_repo::format_time(commit.committer().when()),
+        }
     }
 }
@@ -0,0 +1,97 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::git_repo::{GitRepo, GitRepoError};
+
+const HOOK_NAME: &str = "prepare-commit-msg";
+const HOOK_MARKER: &str = "# Installed by lumen";
+const HOOK_SCRIPT: &str = "#!/bin/sh
+# Installed by lumen: fills in the commit message from the staged changes.
+# Remove with `lumen hook uninstall`.
+lumen hook run \"$1\" \"$2\" \"$3\" || true
+";
+
+#[derive(Debug, Clone)]
+pub enum GitHookError {
+    CommandError(String),
+    HookExists(PathBuf),
+    NotInstalled(PathBuf),
+}
+
+impl From<io::Error> for GitHookError {
+    fn from(err: io::Error) -> GitHookError {
+        GitHookError::CommandError(err.to_string())
+    }
+}
+
+impl From<GitRepoError> for GitHookError {
+    fn from(err: GitRepoError) -> GitHookError {
+        GitHookError::CommandError(err.to_string())
+    }
+}
+
+impl std::fmt::Display for GitHookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitHookError::CommandError(err) => write!(f, "{err}"),
+            GitHookError::HookExists(path) => write!(
+                f,
+                "A hook not managed by lumen already exists at '{}' (use --force to replace it)",
+                path.display()
+            ),
+            GitHookError::NotInstalled(path) => {
+                write!(f, "No lumen hook is installed at '{}'", path.display())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GitHook {
+    pub path: PathBuf,
+}
+
+impl GitHook {
+    /// Locates the `prepare-commit-msg` hook of the current repository,
+    /// honoring `core.hooksPath`.
+    pub fn prepare_commit_msg(repo: &GitRepo) -> Result<Self, GitHookError> {
+        Ok(GitHook {
+            path: repo.hooks_dir()?.join(HOOK_NAME),
+        })
+    }
+
+    pub fn is_installed(&self) -> bool {
+        fs::read_to_string(&self.path)
+            .map(|script| script.contains(HOOK_MARKER))
+            .unwrap_or(false)
+    }
+
+    pub fn install(&self, force: bool) -> Result<(), GitHookError> {
+        if self.path.exists() && !self.is_installed() && !force {
+            return Err(GitHookError::HookExists(self.path.clone()));
+        }
+
+        if let Some(hooks_dir) = self.path.parent() {
+            fs::create_dir_all(hooks_dir)?;
+        }
+        fs::write(&self.path, HOOK_SCRIPT)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn uninstall(&self) -> Result<(), GitHookError> {
+        if !self.is_installed() {
+            return Err(GitHookError::NotInstalled(self.path.clone()));
+        }
+
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
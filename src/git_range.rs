@@ -0,0 +1,223 @@
+use crate::diff;
+use crate::git_commit::GitCommit;
+use crate::git_repo::{self, GitRepo, GitRepoError};
+use crate::path_filter::PathFilter;
+
+#[derive(Debug, Clone)]
+pub enum GitRangeError {
+    CommandError(String),
+    InvalidRevision(String, String),
+    EmptyRange(String),
+}
+
+impl From<GitRepoError> for GitRangeError {
+    fn from(err: GitRepoError) -> GitRangeError {
+        GitRangeError::CommandError(err.to_string())
+    }
+}
+
+impl std::fmt::Display for GitRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitRangeError::CommandError(err) => write!(f, "{err}"),
+            GitRangeError::InvalidRevision(range, rev) => {
+                write!(f, "Revision '{rev}' in range '{range}' not found")
+            }
+            GitRangeError::EmptyRange(range) => write!(f, "Range '{range}' contains no commits"),
+        }
+    }
+}
+
+/// A revision range such as `main..feature` or `v1.2.0...v1.3.0`: every
+/// non-merge commit it selects, oldest first, and the combined diff of the
+/// range. The commits only carry their metadata, not their own diffs.
+///
+/// Both `A..B` and `A...B` select what `B` changed since it diverged from
+/// `A`, as reviewing a branch needs: the commits on `B`'s side only, and
+/// their diff against the merge base. Commits only on `A`'s side, which
+/// `git log A...B` would list as well, are left out, as their changes are
+/// not in the diff.
+#[derive(Clone, Debug)]
+pub struct GitRange {
+    pub range: String,
+    to: String,
+    pub commits: Vec<GitCommit>,
+    pub diff: String,
+    pub files: Vec<String>,
+    pub omitted_files: Vec<String>,
+}
+
+impl GitRange {
+    pub fn is_range(rev: &str) -> bool {
+        rev.contains("..")
+    }
+
+    pub fn new(repo: &GitRepo, range: String, filter: &PathFilter) -> Result<Self, GitRangeError> {
+        Self::build(repo, range, Some(filter))
+    }
+
+    /// The commits of `range` without its diff, for when only their
+    /// messages are needed: `diff` and the file lists are left empty.
+    pub fn log(repo: &GitRepo, range: String) -> Result<Self, GitRangeError> {
+        Self::build(repo, range, None)
+    }
+
+    fn build(
+        repo: &GitRepo,
+        range: String,
+        filter: Option<&PathFilter>,
+    ) -> Result<Self, GitRangeError> {
+        let (from, to) = Self::parse(&range);
+        let resolve = |rev: &str| {
+            repo.commit(rev)
+                .map_err(|_| GitRangeError::InvalidRevision(range.clone(), rev.to_string()))
+        };
+        let (from_commit, to_commit) = (resolve(&from)?, resolve(&to)?);
+
+        // Commits without changes have nothing to contribute to the summary
+        let commits: Vec<GitCommit> = repo
+            .range_commits(&from_commit, &to_commit)?
+            .iter()
+            .filter(|commit| !git_repo::is_empty_commit(commit))
+            .map(GitCommit::metadata)
+            .collect();
+        if commits.is_empty() {
+            return Err(GitRangeError::EmptyRange(range));
+        }
+
+        // The changes `to` introduces since it diverged from `from`, so that
+        // `main..feature` shows the feature branch only
+        let (diff, omitted_files) = match filter {
+            Some(filter) => filter.apply(&repo.range_diff(&from_commit, &to_commit)?),
+            None => Default::default(),
+        };
+
+        Ok(GitRange {
+            files: diff::files(&diff),
+            diff: diff::replace_binary(&diff),
+            omitted_files,
+            range,
+            to,
+            commits,
+        })
+    }
+
+    /// The tag pointing at the end of the range, if any, e.g. `v1.3.0` for
+    /// `v1.2.0..v1.3.0`.
+    pub fn tag(&self, repo: &GitRepo) -> Result<Option<String>, GitRangeError> {
+        Ok(repo.tag_at(&repo.commit(&self.to)?)?)
+    }
+
+    /// Splits `A..B` and `A...B` into their endpoints. Like git, an omitted
+    /// endpoint defaults to `HEAD`.
+    fn parse(range: &str) -> (String, String) {
+        let (from, to) = range
+            .split_once("...")
+            .or_else(|| range.split_once(".."))
+            .unwrap_or((range, ""));
+
+        let endpoint = |rev: &str| {
+            if rev.is_empty() {
+                "HEAD".to_string()
+            } else {
+                rev.to_string()
+            }
+        };
+
+        (endpoint(from), endpoint(to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git_repo::fixture::Fixture;
+
+    fn subjects(range: &GitRange) -> Vec<&str> {
+        range
+            .commits
+            .iter()
+            .map(|commit| commit.message.as_str())
+            .collect()
+    }
+
+    /// `main` with a feature branch that diverged from it: both have a commit
+    /// the other lacks.
+    fn diverged() -> Fixture {
+        let repo = Fixture::new();
+        repo.commit("Initial commit", &[("README.md", Some(b"# Demo\n"))]);
+        repo.branch("feature");
+        repo.commit("Fix typo on main", &[("README.md", Some(b"# Demo app\n"))]);
+        repo.checkout("feature");
+        repo.commit("Add parser", &[("src/parser.rs", Some(b"fn parse() {}\n"))]);
+        repo.commit(
+            "Test parser",
+            &[("tests/parser.rs", Some(b"#[test]\nfn t() {}\n"))],
+        );
+        repo
+    }
+
+    fn range(repo: &Fixture, range: &str) -> GitRange {
+        GitRange::new(&repo.repo, range.to_string(), &PathFilter::default()).unwrap()
+    }
+
+    #[test]
+    fn selects_the_commits_of_a_branch() {
+        let repo = diverged();
+        let range = range(&repo, "main..feature");
+
+        assert_eq!(subjects(&range), ["Add parser", "Test parser"]);
+        assert_eq!(range.files, ["src/parser.rs", "tests/parser.rs"]);
+        assert!(!range.diff.contains("README.md"));
+    }
+
+    #[test]
+    fn logs_the_same_commits_without_a_diff() {
+        let repo = diverged();
+        let log = GitRange::log(&repo.repo, "main..feature".to_string()).unwrap();
+
+        assert_eq!(subjects(&log), subjects(&range(&repo, "main..feature")));
+        assert!(log.diff.is_empty() && log.files.is_empty());
+    }
+
+    #[test]
+    fn selects_the_last_commits() {
+        let repo = diverged();
+        repo.commit("Document parser", &[("docs/parser.md", Some(b"Parses.\n"))]);
+        let range = range(&repo, "HEAD~2..HEAD");
+
+        assert_eq!(subjects(&range), ["Test parser", "Document parser"]);
+        assert_eq!(range.files, ["docs/parser.md", "tests/parser.rs"]);
+    }
+
+    #[test]
+    fn symmetric_ranges_only_select_the_commits_in_the_diff() {
+        let repo = diverged();
+        repo.checkout("main");
+        repo.tag("v1");
+        repo.checkout("feature");
+        repo.tag("v2");
+        let range = range(&repo, "v1...v2");
+
+        // "Fix typo on main" is only on v1's side, and not in the diff
+        assert_eq!(subjects(&range), ["Add parser", "Test parser"]);
+        assert_eq!(range.files, ["src/parser.rs", "tests/parser.rs"]);
+    }
+
+    #[test]
+    fn reports_empty_and_invalid_ranges() {
+        let repo = diverged();
+        let err = |range: &str| {
+            GitRange::new(&repo.repo, range.to_string(), &PathFilter::default()).unwrap_err()
+        };
+
+        assert!(matches!(
+            err("feature..main~1"),
+            GitRangeError::EmptyRange(_)
+        ));
+        assert!(matches!(
+            err("main..nope"),
+            GitRangeError::InvalidRevision(_, rev) if rev == "nope"
+        ));
+    }
+}
@@ -0,0 +1,750 @@
+use std::path::{Path, PathBuf};
+
+use git2::{
+    Commit, Delta, Diff, DiffFile, DiffFormat, DiffOptions, ErrorCode, Patch, Repository, Sort,
+    Time, Tree,
+};
+
+use crate::path_filter::PathFilter;
+
+/// Width of the stat summary that precedes the diff of a commit.
+const STAT_WIDTH: usize = 80;
+
+#[derive(Debug, Clone)]
+pub enum GitRepoError {
+    NotARepository(PathBuf),
+    BareRepository(PathBuf),
+    InvalidRevision(String),
+    GitError(String),
+}
+
+impl From<git2::Error> for GitRepoError {
+    fn from(err: git2::Error) -> GitRepoError {
+        GitRepoError::GitError(err.message().to_string())
+    }
+}
+
+impl std::fmt::Display for GitRepoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitRepoError::NotARepository(dir) => write!(
+                f,
+                "'{}' is not in a git repository (or any of its parent directories)",
+                dir.display()
+            ),
+            GitRepoError::BareRepository(dir) => write!(
+                f,
+                "'{}' is a bare repository, which has no working tree or staged changes",
+                dir.display()
+            ),
+            GitRepoError::InvalidRevision(rev) => write!(f, "Revision '{rev}' not found"),
+            GitRepoError::GitError(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// The repository lumen runs in, found like git finds it: from the current
+/// directory upwards, honoring `GIT_DIR` and `GIT_CEILING_DIRECTORIES`.
+/// Revisions, metadata and diffs are read in-process rather than by running
+/// git for each of them.
+pub struct GitRepo {
+    repo: Repository,
+}
+
+impl GitRepo {
+    pub fn open() -> Result<Self, GitRepoError> {
+        match Repository::open_from_env() {
+            Ok(repo) => Ok(GitRepo { repo }),
+            Err(err) if err.code() == ErrorCode::NotFound => Err(GitRepoError::NotARepository(
+                std::env::current_dir().unwrap_or_default(),
+            )),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The top-level directory of the working tree, or `None` for bare
+    /// repositories.
+    pub fn root(&self) -> Option<&Path> {
+        self.repo.workdir()
+    }
+
+    fn workdir(&self) -> Result<&Path, GitRepoError> {
+        self.repo
+            .workdir()
+            .ok_or_else(|| GitRepoError::BareRepository(self.repo.path().to_path_buf()))
+    }
+
+    /// The commit `rev` resolves to, e.g. `HEAD~2`, `v1.2.0` or a hash.
+    pub fn commit(&self, rev: &str) -> Result<Commit<'_>, GitRepoError> {
+        self.repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| GitRepoError::InvalidRevision(rev.to_string()))
+    }
+
+    /// The changes of `commit` like `git diff-tree -p --compact-summary`
+    /// shows them: its hash, a stat summary and the patch against its first
+    /// parent. Empty for commits without changes. Files that `filter`
+    /// excludes are left out of the summary, but not the patch.
+    pub fn commit_diff(
+        &self,
+        commit: &Commit,
+        filter: &PathFilter,
+    ) -> Result<String, GitRepoError> {
+        let diff = self.commit_changes(commit)?;
+        if diff.deltas().len() == 0 {
+            return Ok(String::new());
+        }
+
+        Ok(format!(
+            "{}\n{}\n{}",
+            commit.id(),
+            compact_summary(&diff, filter)?,
+            patch(&diff)?
+        ))
+    }
+
+    /// The stat summary of `commit` alone, like `git show --compact-summary
+    /// --format=`. Empty for commits without changes.
+    pub fn commit_stat(&self, commit: &Commit) -> Result<String, GitRepoError> {
+        let diff = self.commit_changes(commit)?;
+        if diff.deltas().len() == 0 {
+            return Ok(String::new());
+        }
+
+        compact_summary(&diff, &PathFilter::default())
+    }
+
+    /// The most recent commits reachable from `HEAD`, newest first, like
+    /// `git log -n limit`. Empty before the first commit.
+    pub fn log(&self, limit: usize) -> Result<Vec<Commit<'_>>, GitRepoError> {
+        let Ok(head) = self.repo.head() else {
+            return Ok(Vec::new());
+        };
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push(head.peel_to_commit()?.id())?;
+
+        revwalk
+            .take(limit)
+            .map(|id| Ok(self.repo.find_commit(id?)?))
+            .collect()
+    }
+
+    fn commit_changes(&self, commit: &Commit) -> Result<Diff<'_>, GitRepoError> {
+        let parent = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        self.diff_trees(parent.as_ref(), &commit.tree()?)
+    }
+
+    fn diff_trees(&self, old: Option<&Tree>, new: &Tree) -> Result<Diff<'_>, GitRepoError> {
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(old, Some(new), Some(&mut diff_options()))?;
+        diff.find_similar(None)?;
+
+        Ok(diff)
+    }
+
+    /// The staged changes, like `git diff --staged`.
+    pub fn staged_diff(&self) -> Result<String, GitRepoError> {
+        self.workdir()?;
+
+        // Before the first commit everything in the index is new
+        let head = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_tree().ok());
+        let index = self.repo.index()?;
+        let mut diff =
+            self.repo
+                .diff_tree_to_index(head.as_ref(), Some(&index), Some(&mut diff_options()))?;
+        diff.find_similar(None)?;
+
+        patch(&diff)
+    }
+
+    /// The changes `to` introduces since it diverged from `from`, like
+    /// `git diff from...to`.
+    pub fn range_diff(&self, from: &Commit, to: &Commit) -> Result<String, GitRepoError> {
+        let base = self
+            .repo
+            .find_commit(self.repo.merge_base(from.id(), to.id())?)?;
+        patch(&self.diff_trees(Some(&base.tree()?), &to.tree()?)?)
+    }
+
+    /// The non-merge commits reachable from `to` but not from `from`, oldest
+    /// first, like `git rev-list --reverse --no-merges from..to`. These are
+    /// the commits whose changes [`GitRepo::range_diff`] shows.
+    pub fn range_commits(
+        &self,
+        from: &Commit,
+        to: &Commit,
+    ) -> Result<Vec<Commit<'_>>, GitRepoError> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE)?;
+        revwalk.push(to.id())?;
+        revwalk.hide(from.id())?;
+
+        let mut commits = Vec::new();
+        for id in revwalk {
+            let commit = self.repo.find_commit(id?)?;
+            if commit.parent_count() <= 1 {
+                commits.push(commit);
+            }
+        }
+
+        Ok(commits)
+    }
+
+    /// A tag pointing at `commit`, if any.
+    pub fn tag_at(&self, commit: &Commit) -> Result<Option<String>, GitRepoError> {
+        for name in self.repo.tag_names(None)?.iter().flatten().flatten() {
+            let target = self
+                .repo
+                .revparse_single(&format!("refs/tags/{name}"))
+                .and_then(|object| object.peel_to_commit());
+
+            if target.is_ok_and(|target| target.id() == commit.id()) {
+                return Ok(Some(name.to_string()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The name of the checked out branch, or `None` when HEAD is detached.
+    /// A branch without commits yet has a name as well.
+    pub fn current_branch(&self) -> Result<Option<String>, GitRepoError> {
+        let head = self.repo.find_reference("HEAD")?;
+
+        Ok(head
+            .symbolic_target()?
+            .and_then(|target| target.strip_prefix("refs/heads/"))
+            .map(String::from))
+    }
+
+    /// The branch `origin/HEAD` points to, e.g. `origin/main`.
+    pub fn remote_default_branch(&self) -> Option<String> {
+        let reference = self.repo.find_reference("refs/remotes/origin/HEAD").ok()?;
+        let target = reference.symbolic_target().ok()??;
+
+        target.strip_prefix("refs/remotes/").map(String::from)
+    }
+
+    pub fn has_branch(&self, name: &str) -> bool {
+        self.repo
+            .find_reference(&format!("refs/heads/{name}"))
+            .is_ok()
+    }
+
+    /// The directory git runs hooks from: `core.hooksPath`, relative to the
+    /// working tree, or the `hooks` directory of the repository. Worktrees
+    /// share the hooks of the main repository.
+    pub fn hooks_dir(&self) -> Result<PathBuf, GitRepoError> {
+        match self.repo.config()?.get_path("core.hooksPath") {
+            Ok(path) => Ok(self.repo.workdir().unwrap_or(self.repo.path()).join(path)),
+            Err(err) if err.code() == ErrorCode::NotFound => {
+                Ok(self.repo.commondir().join("hooks"))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Whether `commit` leaves the tree of its first parent as it is, like
+/// commits made with `git commit --allow-empty`.
+pub fn is_empty_commit(commit: &Commit) -> bool {
+    match commit.parents().next() {
+        Some(parent) => parent.tree_id() == commit.tree_id(),
+        None => commit.tree().is_ok_and(|tree| tree.is_empty()),
+    }
+}
+
+/// The options git diffs with by default.
+fn diff_options() -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options.indent_heuristic(true);
+    options
+}
+
+/// The changes to a file in a stat summary.
+enum FileStat {
+    /// Insertions and deletions.
+    Lines(usize, usize),
+    /// The old and new size in bytes.
+    Binary(u64, u64),
+}
+
+/// An overview of `diff` like `git diff --compact-summary`: the number of
+/// changed lines per file, with `(new)` and `(gone)` for added and deleted
+/// files, the sizes of binary files, and the totals. Files that `filter`
+/// excludes are left out.
+fn compact_summary(diff: &Diff, filter: &PathFilter) -> Result<String, GitRepoError> {
+    let path = |file: DiffFile| {
+        file.path()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    let mut files: Vec<(String, FileStat)> = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        // Deleted files only have a path on the old side
+        let file = match delta.status() {
+            Delta::Deleted => delta.old_file(),
+            _ => delta.new_file(),
+        };
+        if filter.is_excluded(&path(file)) {
+            continue;
+        }
+
+        let name = match delta.status() {
+            Delta::Added => format!("{} (new)", path(delta.new_file())),
+            Delta::Deleted => format!("{} (gone)", path(delta.old_file())),
+            Delta::Renamed | Delta::Copied => {
+                rename(&path(delta.old_file()), &path(delta.new_file()))
+            }
+            _ => path(delta.new_file()),
+        };
+
+        let changes = match Patch::from_diff(diff, index)? {
+            Some(patch) if !patch.delta().flags().is_binary() => {
+                let (_, insertions, deletions) = patch.line_stats()?;
+                FileStat::Lines(insertions, deletions)
+            }
+            Some(patch) => FileStat::Binary(
+                patch.delta().old_file().size(),
+                patch.delta().new_file().size(),
+            ),
+            None => FileStat::Binary(delta.old_file().size(), delta.new_file().size()),
+        };
+        files.push((name, changes));
+    }
+
+    let name_width = files.iter().map(|(name, _)| name.chars().count()).max();
+    let name_width = name_width.unwrap_or_default();
+    let most = files
+        .iter()
+        .filter_map(|(_, changes)| match changes {
+            FileStat::Lines(insertions, deletions) => Some(insertions + deletions),
+            FileStat::Binary(..) => None,
+        })
+        .max()
+        .unwrap_or_default();
+    // Counts are aligned with "Bin" when there are binary files
+    let binary = files
+        .iter()
+        .any(|(_, changes)| matches!(changes, FileStat::Binary(..)));
+    let count_width = match binary {
+        true => most.to_string().len().max(3),
+        false => most.to_string().len(),
+    };
+    let graph_width = STAT_WIDTH
+        .saturating_sub(name_width + count_width + 4)
+        .max(10);
+
+    let mut summary = String::new();
+    let (mut insertions, mut deletions) = (0, 0);
+    for (name, changes) in &files {
+        let (added, removed) = match *changes {
+            FileStat::Lines(added, removed) => (added, removed),
+            FileStat::Binary(old_size, new_size) => {
+                summary.push_str(&format!(
+                    " {name:<name_width$} | {:>count_width$} {old_size} -> {new_size} bytes\n",
+                    "Bin"
+                ));
+                continue;
+            }
+        };
+        insertions += added;
+        deletions += removed;
+
+        // Like git, the graph is scaled down when the largest change does
+        // not fit, keeping at least one column for every change
+        let (plus, minus) = match most > graph_width {
+            true => (
+                scale(added, most, graph_width),
+                scale(removed, most, graph_width),
+            ),
+            false => (added, removed),
+        };
+        let graph = format!("{}{}", "+".repeat(plus), "-".repeat(minus));
+        let line = format!(
+            " {name:<name_width$} | {:>count_width$} {graph}",
+            added + removed
+        );
+        summary.push_str(line.trim_end());
+        summary.push('\n');
+    }
+
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("{count} {noun}"),
+        _ => format!("{count} {noun}s"),
+    };
+    // Like git, both counts are shown when neither is, e.g. for binary files
+    summary.push_str(&format!(" {} changed", plural(files.len(), "file")));
+    if insertions > 0 || deletions == 0 {
+        summary.push_str(&format!(", {}(+)", plural(insertions, "insertion")));
+    }
+    if deletions > 0 || insertions == 0 {
+        summary.push_str(&format!(", {}(-)", plural(deletions, "deletion")));
+    }
+    summary.push('\n');
+
+    Ok(summary)
+}
+
+/// A renamed path like git shows it in stat summaries, with the common
+/// leading and trailing directories outside of braces:
+/// `src/{lib.rs => core.rs}`.
+fn rename(old: &str, new: &str) -> String {
+    let (a, b) = (old.as_bytes(), new.as_bytes());
+
+    // The common prefix up to and including its last slash
+    let prefix = a
+        .iter()
+        .zip(b)
+        .take_while(|(a, b)| a == b)
+        .enumerate()
+        .filter(|(_, (c, _))| **c == b'/')
+        .map(|(i, _)| i + 1)
+        .last()
+        .unwrap_or(0);
+
+    // The common suffix from its first slash, which may share the slash
+    // that ends the prefix
+    let shared = usize::from(prefix > 0);
+    let limit = a.len().min(b.len()) + shared - prefix;
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take(limit)
+        .take_while(|(a, b)| a == b)
+        .enumerate()
+        .filter(|(_, (c, _))| **c == b'/')
+        .map(|(i, _)| i + 1)
+        .last()
+        .unwrap_or(0);
+
+    if prefix + suffix == 0 {
+        return format!("{old} => {new}");
+    }
+
+    let middle = |path: &str| {
+        let end = path.len().saturating_sub(suffix).max(prefix);
+        path[prefix..end].to_string()
+    };
+    format!(
+        "{}{{{} => {}}}{}",
+        &old[..prefix],
+        middle(old),
+        middle(new),
+        &old[old.len() - suffix..]
+    )
+}
+
+fn scale(count: usize, most: usize, width: usize) -> usize {
+    match count {
+        0 => 0,
+        count => 1 + count * (width - 1) / most,
+    }
+}
+
+/// `diff` as a unified patch with `diff --git` headers.
+fn patch(diff: &Diff) -> Result<String, GitRepoError> {
+    let mut patch = Vec::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin() as u8);
+        }
+        patch.extend_from_slice(line.content());
+        true
+    })?;
+
+    Ok(String::from_utf8_lossy(&patch).into_owned())
+}
+
+/// `time` as `YYYY-MM-DD HH:MM:SS` in its own time zone, like git's
+/// `--date=format:%Y-%m-%d %H:%M:%S`.
+pub fn format_time(time: Time) -> String {
+    let seconds = time.seconds() + i64::from(time.offset_minutes()) * 60;
+    let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// The proleptic Gregorian date `days` after 1970-01-01, see
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Repositories built commit by commit in a temporary directory, for tests.
+#[cfg(test)]
+pub mod fixture {
+    use std::cell::Cell;
+    use std::fs;
+
+    use git2::{build::CheckoutBuilder, Oid, Repository, RepositoryInitOptions, Signature, Time};
+
+    use super::GitRepo;
+
+    pub struct Fixture {
+        pub repo: GitRepo,
+        dir: tempfile::TempDir,
+        /// The time of the next commit, a minute after the previous one.
+        time: Cell<i64>,
+    }
+
+    impl Fixture {
+        /// An empty repository whose first branch is `main`.
+        pub fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let repo = Repository::init_opts(
+                dir.path(),
+                RepositoryInitOptions::new().initial_head("main"),
+            )
+            .unwrap();
+
+            Fixture {
+                repo: GitRepo { repo },
+                dir,
+                time: Cell::new(1_700_000_000),
+            }
+        }
+
+        /// Commits `changes` on the current branch: each file is written
+        /// with its content, or deleted without one.
+        pub fn commit(&self, message: &str, changes: &[(&str, Option<&[u8]>)]) -> Oid {
+            let repo = &self.repo.repo;
+            let mut index = repo.index().unwrap();
+            for (path, content) in changes {
+                let file = self.dir.path().join(path);
+                match content {
+                    Some(content) => {
+                        fs::create_dir_all(file.parent().unwrap()).unwrap();
+                        fs::write(&file, content).unwrap();
+                        index.add_path(path.as_ref()).unwrap();
+                    }
+                    None => {
+                        fs::remove_file(&file).unwrap();
+                        index.remove_path(path.as_ref()).unwrap();
+                    }
+                }
+            }
+            index.write().unwrap();
+
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+            self.time.set(self.time.get() + 60);
+            let author =
+                Signature::new("Dev", "dev@example.com", &Time::new(self.time.get(), 0)).unwrap();
+
+            repo.commit(
+                Some("HEAD"),
+                &author,
+                &author,
+                message,
+                &tree,
+                parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+            )
+            .unwrap()
+        }
+
+        /// Creates branch `name` at the current commit.
+        pub fn branch(&self, name: &str) {
+            let repo = &self.repo.repo;
+            let head = repo.head().unwrap().peel_to_commit().unwrap();
+            repo.branch(name, &head, false).unwrap();
+        }
+
+        /// Checks out branch `name`.
+        pub fn checkout(&self, name: &str) {
+            let repo = &self.repo.repo;
+            repo.set_head(&format!("refs/heads/{name}")).unwrap();
+            repo.checkout_head(Some(CheckoutBuilder::new().force()))
+                .unwrap();
+        }
+
+        /// Tags the current commit as `name`.
+        pub fn tag(&self, name: &str) {
+            let repo = &self.repo.repo;
+            let head = repo.head().unwrap().peel(git2::ObjectType::Commit).unwrap();
+            repo.tag_lightweight(name, &head, false).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixture::Fixture;
+    use super::*;
+
+    fn time(seconds: i64, offset_minutes: i32) -> String {
+        format_time(Time::new(seconds, offset_minutes))
+    }
+
+    #[test]
+    fn formats_times_in_their_time_zone() {
+        assert_eq!(time(0, 0), "1970-01-01 00:00:00");
+        assert_eq!(time(1_709_208_000, 0), "2024-02-29 12:00:00");
+        assert_eq!(time(1_709_251_199, 0), "2024-02-29 23:59:59");
+        assert_eq!(time(1_709_251_200, 0), "2024-03-01 00:00:00");
+        assert_eq!(time(951_782_400, 0), "2000-02-29 00:00:00");
+        assert_eq!(time(0, -300), "1969-12-31 19:00:00");
+        assert_eq!(time(1_709_208_000, -570), "2024-02-29 02:30:00");
+        assert_eq!(time(1_709_208_000, 330), "2024-02-29 17:30:00");
+    }
+
+    #[test]
+    fn formats_times_before_1970() {
+        assert_eq!(time(-1, 0), "1969-12-31 23:59:59");
+        assert_eq!(time(-86_400 * 365, 0), "1969-01-01 00:00:00");
+        assert_eq!(time(-2_208_988_800, 0), "1900-01-01 00:00:00");
+        // 1900 was not a leap year
+        assert_eq!(time(-2_203_891_200, 0), "1900-03-01 00:00:00");
+        assert_eq!(time(-11_644_473_600, 60), "1601-01-01 01:00:00");
+    }
+
+    #[test]
+    fn shortens_renames_like_git() {
+        assert_eq!(rename("lib.rs", "core.rs"), "lib.rs => core.rs");
+        assert_eq!(
+            rename("src/lib.rs", "src/core.rs"),
+            "src/{lib.rs => core.rs}"
+        );
+        assert_eq!(rename("a/b/c.rs", "a/d/c.rs"), "a/{b => d}/c.rs");
+        assert_eq!(rename("src/a.rs", "src/sub/a.rs"), "src/{ => sub}/a.rs");
+        assert_eq!(rename("old/mod.rs", "new/mod.rs"), "{old => new}/mod.rs");
+    }
+
+    /// What `git diff-tree -p --compact-summary --root -M` printed for each
+    /// commit of [`history`] before diffs were read with libgit2, apart from
+    /// the similarity index of renames, which libgit2 scores differently.
+    const GIT_OUTPUT: [&str; 3] = [
+        "d0e1cb88114c7630af094423ea8b81e7eb19d067
+ assets/logo.png (new) | Bin 0 -> 7 bytes
+ src/lib.rs (new)      |   4 ++++
+ 2 files changed, 4 insertions(+)
+
+diff --git a/assets/logo.png b/assets/logo.png
+new file mode 100644
+index 0000000..d0463d4
+Binary files /dev/null and b/assets/logo.png differ
+diff --git a/src/lib.rs b/src/lib.rs
+new file mode 100644
+index 0000000..c9b0176
+--- /dev/null
++++ b/src/lib.rs
+@@ -0,0 +1,4 @@
++fn a() {}
++fn b() {}
++fn c() {}
++fn d() {}
+",
+        "31f135f2ef59bc364b5e50b67e494870a74a474b
+ src/{lib.rs => parser/mod.rs} | 1 +
+ 1 file changed, 1 insertion(+)
+
+diff --git a/src/lib.rs b/src/parser/mod.rs
+rename from src/lib.rs
+rename to src/parser/mod.rs
+index c9b0176..c521009 100644
+--- a/src/lib.rs
++++ b/src/parser/mod.rs
+@@ -2,3 +2,4 @@ fn a() {}
+ fn b() {}
+ fn c() {}
+ fn d() {}
++fn e() {}
+",
+        "4b168ca50e59f8218f5af69f67839860104714b7
+ assets/logo.png | Bin 7 -> 8 bytes
+ 1 file changed, 0 insertions(+), 0 deletions(-)
+
+diff --git a/assets/logo.png b/assets/logo.png
+index d0463d4..1015cd1 100644
+Binary files a/assets/logo.png and b/assets/logo.png differ
+",
+    ];
+
+    /// A root commit, a rename with a change and a binary update.
+    fn history(repo: &Fixture) -> [git2::Oid; 3] {
+        let parser = b"fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\n";
+        [
+            repo.commit(
+                "Add parser",
+                &[
+                    ("src/lib.rs", Some(parser)),
+                    ("assets/logo.png", Some(b"\x89PNG\0\x01\x02")),
+                ],
+            ),
+            repo.commit(
+                "Move parser",
+                &[
+                    ("src/lib.rs", None),
+                    (
+                        "src/parser/mod.rs",
+                        Some(b"fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\nfn e() {}\n"),
+                    ),
+                ],
+            ),
+            repo.commit(
+                "Update logo",
+                &[("assets/logo.png", Some(b"\x89PNG\0\x03\x04\x05"))],
+            ),
+        ]
+    }
+
+    #[test]
+    fn commit_diffs_match_git() {
+        let repo = Fixture::new();
+        for (oid, expected) in history(&repo).into_iter().zip(GIT_OUTPUT) {
+            let commit = repo.repo.repo.find_commit(oid).unwrap();
+            let diff = repo
+                .repo
+                .commit_diff(&commit, &PathFilter::default())
+                .unwrap();
+            let diff: String = diff
+                .split_inclusive('\n')
+                .filter(|line| !line.starts_with("similarity index "))
+                .collect();
+
+            assert_eq!(diff, expected);
+        }
+    }
+
+    #[test]
+    fn leaves_excluded_files_out_of_the_summary() {
+        let repo = Fixture::new();
+        let oid = history(&repo)[0];
+        let commit = repo.repo.repo.find_commit(oid).unwrap();
+        let filter = PathFilter::new(&[], &["assets/".to_string()]).unwrap();
+
+        let diff = repo.repo.commit_diff(&commit, &filter).unwrap();
+
+        assert!(diff.contains("\n src/lib.rs (new) | 4 ++++\n 1 file changed, 4 insertions(+)\n"));
+    }
+}
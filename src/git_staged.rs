@@ -1,9 +1,15 @@
-use std::{io, process::Command, string::FromUtf8Error};
+use std::{io, path::Path, process::Command};
+
+use crate::diff;
+use crate::git_repo::{GitRepo, GitRepoError};
+use crate::path_filter::PathFilter;
 
 #[derive(Debug, Clone)]
 pub enum GitStagedError {
     CommandError(String),
     EmptyDiff(),
+    AllExcluded(Vec<String>),
+    CommitFailed(),
 }
 
 impl From<io::Error> for GitStagedError {
@@ -12,8 +18,8 @@ impl From<io::Error> for GitStagedError {
     }
 }
 
-impl From<FromUtf8Error> for GitStagedError {
-    fn from(err: FromUtf8Error) -> GitStagedError {
+impl From<GitRepoError> for GitStagedError {
+    fn from(err: GitRepoError) -> GitStagedError {
         GitStagedError::CommandError(err.to_string())
     }
 }
@@ -23,6 +29,12 @@ impl std::fmt::Display for GitStagedError {
         match self {
             GitStagedError::CommandError(err) => write!(f, "{err}"),
             GitStagedError::EmptyDiff() => write!(f, "Diff for staged changes is empty"),
+            GitStagedError::AllExcluded(files) => write!(
+                f,
+                "All staged changes are excluded by path filters ({})",
+                files.join(", ")
+            ),
+            GitStagedError::CommitFailed() => write!(f, "git commit did not succeed"),
         }
     }
 }
@@ -30,27 +42,51 @@ impl std::fmt::Display for GitStagedError {
 #[derive(Clone, Debug)]
 pub struct GitStaged {
     pub diff: String,
+    pub files: Vec<String>,
+    pub omitted_files: Vec<String>,
 }
 
 impl GitStaged {
-    pub fn new() -> Result<Self, GitStagedError> {
+    pub fn new(repo: &GitRepo, filter: &PathFilter) -> Result<Self, GitStagedError> {
+        let (diff, omitted_files) = Self::get_staged_diff(repo, filter)?;
+
         Ok(GitStaged {
-            diff: Self::get_staged_diff()?,
+            files: diff::files(&diff),
+            diff,
+            omitted_files,
         })
     }
 
-    fn get_staged_diff() -> Result<String, GitStagedError> {
-        let output = Command::new("git")
-            .args(["diff", "--staged"])
-            .output()
-            .expect("Failed to execute git command");
-
-        let diff = String::from_utf8(output.stdout)?;
+    fn get_staged_diff(
+        repo: &GitRepo,
+        filter: &PathFilter,
+    ) -> Result<(String, Vec<String>), GitStagedError> {
+        let (diff, omitted_files) = filter.apply(&repo.staged_diff()?);
+        let diff = diff::replace_binary(&diff);
 
+        if diff.is_empty() && !omitted_files.is_empty() {
+            return Err(GitStagedError::AllExcluded(omitted_files));
+        }
         if diff.is_empty() {
             return Err(GitStagedError::EmptyDiff());
         }
 
-        Ok(diff)
+        Ok((diff, omitted_files))
+    }
+
+    /// Commits the staged changes with the message stored in `message_file`.
+    /// git's output and hooks are attached to the current terminal.
+    pub fn commit(message_file: &Path) -> Result<(), GitStagedError> {
+        let status = Command::new("git")
+            .arg("commit")
+            .arg("-F")
+            .arg(message_file)
+            .status()?;
+
+        if !status.success() {
+            return Err(GitStagedError::CommitFailed());
+        }
+
+        Ok(())
     }
 }
@@ -1,27 +1,42 @@
-use clap::{command, Parser, Subcommand, ValueEnum};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use config::LumenConfig;
 use error::LumenError;
-use reqwest;
+use serde::Deserialize;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 use std::process;
-use tokio;
+use std::time::Duration;
 
+mod ai_prompt;
+mod cache;
+mod changelog;
 mod command;
+mod commit_message;
+mod commit_picker;
+mod config;
+mod conventional_commit;
+mod diff;
 mod error;
+mod git_branch;
 mod git_commit;
+mod git_hook;
+mod git_range;
+mod git_repo;
 mod git_staged;
+mod markdown;
+mod path_filter;
+mod pr_template;
+mod prompt_template;
 mod provider;
+mod secrets;
+mod summary;
 
 #[derive(Parser)]
 #[command(name = "lumen")]
 #[command(about = "AI-powered CLI tool for git commit summaries", long_about = None)]
 struct Cli {
-    #[arg(
-        value_enum,
-        short = 'p',
-        long = "provider",
-        env("LUMEN_AI_PROVIDER"),
-        default_value = "phind"
-    )]
-    provider: ProviderType,
+    #[arg(value_enum, short = 'p', long = "provider", env("LUMEN_AI_PROVIDER"))]
+    provider: Option<ProviderType>,
 
     #[arg(short = 'k', long = "api-key", env = "LUMEN_API_KEY")]
     api_key: Option<String>,
@@ -29,16 +44,109 @@ struct Cli {
     #[arg(short = 'm', long = "model", env = "LUMEN_AI_MODEL")]
     model: Option<String>,
 
+    #[arg(long = "base-url", env = "LUMEN_BASE_URL")]
+    base_url: Option<String>,
+
+    /// Only send the diff of paths matching these globs
+    #[arg(long = "include", value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// Leave paths matching these globs out of the diff
+    #[arg(long = "exclude", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Providers to try in order when the provider is unavailable
+    #[arg(
+        value_enum,
+        long = "fallback",
+        value_delimiter = ',',
+        env = "LUMEN_FALLBACK"
+    )]
+    fallback: Vec<ProviderType>,
+
+    /// Neither use nor store cached responses
+    #[arg(long = "no-cache")]
+    no_cache: bool,
+
+    /// Print Markdown, or a JSON object for scripts and editors
+    #[arg(value_enum, long = "output", env = "LUMEN_OUTPUT")]
+    output: Option<OutputFormat>,
+
+    /// What to do with secrets (keys, tokens) found in the diff
+    #[arg(value_enum, long = "secrets", env = "LUMEN_SECRETS")]
+    secrets: Option<SecretPolicy>,
+
+    /// When to color and style the output
+    #[arg(value_enum, long = "color", env = "LUMEN_COLOR")]
+    color: Option<ColorChoice>,
+
+    /// Show long output in a pager ($PAGER, or less)
+    #[arg(long = "pager", overrides_with = "no_pager")]
+    pager: bool,
+
+    /// Print output directly, even if `pager` is set in the config
+    #[arg(long = "no-pager", overrides_with = "pager")]
+    no_pager: bool,
+
+    /// Print the requests that would be sent to the providers instead of sending them
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
 enum ProviderType {
     Openai,
     Phind,
     Groq,
     Claude,
+    Gemini,
+    Mistral,
+    Deepseek,
+    Ollama,
+    #[serde(rename = "openai-compatible")]
+    OpenaiCompatible,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum CommitFormat {
+    Plain,
+    Conventional,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    /// Markdown for people
+    Text,
+    /// A JSON object for scripts and editors
+    Json,
+}
+
+/// When to render Markdown with colors and styles.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum ColorChoice {
+    /// When writing to a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+/// What to do with secrets found in a diff before it is sent.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum SecretPolicy {
+    /// Replace them with placeholders
+    Redact,
+    /// Stop without sending anything
+    Refuse,
+    /// Send the diff as it is
+    Allow,
 }
 
 #[derive(Subcommand)]
@@ -47,32 +155,199 @@ enum Commands {
         #[arg()]
         sha: String,
     },
-    Suggest,
+    Suggest {
+        #[arg(value_enum, long = "format")]
+        format: Option<CommitFormat>,
+    },
     List,
+    Chat {
+        #[arg(required_unless_present = "staged")]
+        sha: Option<String>,
+
+        #[arg(long = "staged", conflicts_with = "sha")]
+        staged: bool,
+    },
+    Models,
+    Commit {
+        #[arg(value_enum, long = "format")]
+        format: Option<CommitFormat>,
+    },
+    Pr {
+        #[arg(short = 'b', long = "base")]
+        base: Option<String>,
+
+        #[arg(short = 't', long = "template")]
+        template: Option<PathBuf>,
+    },
+    Changelog {
+        #[arg()]
+        range: String,
+
+        #[arg(long = "version")]
+        version: Option<String>,
+
+        #[arg(long = "prepend")]
+        prepend: Option<PathBuf>,
+    },
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    Prompt {
+        #[command(subcommand)]
+        action: PromptAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum PromptAction {
+    Show {
+        #[arg()]
+        name: String,
+
+        #[arg()]
+        rev: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    Clear,
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    Show,
+}
+
+#[derive(Subcommand)]
+enum HookAction {
+    Install {
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+    },
+    Uninstall,
+    Run {
+        #[arg()]
+        message_file: PathBuf,
+
+        /// Where git took the message from; generation is skipped for any source
+        #[arg()]
+        source: Option<String>,
+
+        /// The commit git passes with the `commit` source; that message is kept, so it is unused
+        #[arg(hide = true)]
+        sha: Option<String>,
+
+        #[arg(value_enum, long = "format")]
+        format: Option<CommitFormat>,
+    },
 }
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("\x1b[91m\rError: {e}\x1b[0m");
-        process::exit(1);
+    match run().await {
+        Ok(()) => {}
+        Err(e) => {
+            if std::io::stderr().is_terminal() && !markdown::no_color() {
+                eprintln!("\x1b[91m\rError: {e}\x1b[0m");
+            } else {
+                eprintln!("Error: {e}");
+            }
+            process::exit(1);
+        }
     }
 }
 
 async fn run() -> Result<(), LumenError> {
-    let cli = Cli::parse();
-    let client = reqwest::Client::new();
-    let provider = provider::LumenProvider::new(client, cli.provider, cli.api_key, cli.model)?;
-    let command = command::LumenCommand::new(provider);
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let config = LumenConfig::load(&cli, &matches)?;
+
+    // Installing hooks, showing the config and prompts and managing the
+    // cache do not talk to a provider
+    match cli.command {
+        Commands::Hook {
+            action: HookAction::Install { force },
+        } => return command::LumenCommand::install_hook(force),
+        Commands::Hook {
+            action: HookAction::Uninstall,
+        } => return command::LumenCommand::uninstall_hook(),
+        Commands::Config {
+            action: ConfigAction::Show,
+        } => {
+            println!("{}", config.show());
+            return Ok(());
+        }
+        Commands::Cache {
+            action: CacheAction::Clear,
+        } => return command::LumenCommand::clear_cache(&config),
+        Commands::Cache {
+            action: CacheAction::Stats,
+        } => return command::LumenCommand::cache_stats(&config),
+        Commands::Prompt {
+            action: PromptAction::Show { name, rev },
+        } => return command::LumenCommand::show_prompt(&config, &name, rev),
+        _ => {}
+    }
+
+    // Without timeouts an unreachable or stalled provider hangs instead of
+    // being retried or failing over to the next one
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(config.timeout_secs.value))
+        .build()
+        .map_err(|err| LumenError::UnknownError(err.into()))?;
+    let provider = provider::chain::ProviderChain::new(client, &config)?.with_dry_run(cli.dry_run);
+    let format = config.format.value;
+    let command = command::LumenCommand::new(provider, config)?;
 
     match cli.command {
         Commands::Explain { sha } => {
             command.explain(Some(sha)).await?;
         }
-        Commands::Suggest {} => {
-            command.explain(None).await?;
+        Commands::Suggest { format: flag } => {
+            command.suggest(flag.unwrap_or(format)).await?;
         }
         Commands::List => command.list().await?,
+        // Without a commit, `--staged` was given
+        Commands::Chat { sha, .. } => command.chat(sha).await?,
+        Commands::Models => command.models().await?,
+        Commands::Commit { format: flag } => command.commit(flag.unwrap_or(format)).await?,
+        Commands::Pr { base, template } => command.pr(base, template).await?,
+        Commands::Changelog {
+            range,
+            version,
+            prepend,
+        } => command.changelog(range, version, prepend).await?,
+        Commands::Hook {
+            action:
+                HookAction::Run {
+                    message_file,
+                    source,
+                    sha: _,
+                    format: flag,
+                },
+        } => {
+            command
+                .run_hook(&message_file, source.as_deref(), flag.unwrap_or(format))
+                .await?
+        }
+        Commands::Hook { .. }
+        | Commands::Config { .. }
+        | Commands::Cache { .. }
+        | Commands::Prompt { .. } => {
+            unreachable!("handled before creating the provider")
+        }
     }
 
     Ok(())
@@ -0,0 +1,559 @@
+use std::sync::OnceLock;
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+use crate::ColorChoice;
+
+/// Width used when it cannot be read from the terminal or `$COLUMNS`.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Longer lines are hard to read, so text is not wrapped wider than this
+/// even in wide terminals. Code blocks are never wrapped.
+const MAX_WIDTH: usize = 100;
+
+const THEME: &str = "base16-ocean.dark";
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const ITALIC: &str = "3";
+const STRIKETHROUGH: &str = "9";
+const HEADING: &str = "1;35";
+const TITLE: &str = "1;4;35";
+const CODE: &str = "36";
+const LINK: &str = "4;34";
+
+/// Whether `NO_COLOR` asks for output without colors, see
+/// <https://no-color.org>.
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Whether to color output with `choice`, when writing to a terminal or not.
+pub fn use_color(choice: ColorChoice, terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => terminal && !no_color(),
+    }
+}
+
+/// The width of the terminal on stdout, or `$COLUMNS` when stdout is not a
+/// terminal.
+pub fn terminal_width() -> usize {
+    let width = match terminal_size::terminal_size_of(std::io::stdout()) {
+        Some((terminal_size::Width(width), _)) => width as usize,
+        None => std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH),
+    };
+
+    width.min(MAX_WIDTH)
+}
+
+/// Renders Markdown for the terminal: headings, emphasis, lists, quotes and
+/// links are styled with ANSI escapes, paragraphs are wrapped to the width
+/// and code blocks are syntax highlighted. Without colors the structure is
+/// kept with plain-text markers (`#`, `-`, backticks).
+pub struct Markdown {
+    color: bool,
+    width: usize,
+}
+
+impl Markdown {
+    pub fn new(color: bool, width: usize) -> Self {
+        Markdown { color, width }
+    }
+
+    /// Renders `text`, without a trailing newline.
+    pub fn render(&self, text: &str) -> String {
+        let mut renderer = Renderer {
+            markdown: self,
+            out: String::new(),
+            inline: String::new(),
+            styles: Vec::new(),
+            indent: Vec::new(),
+            marker: None,
+            lists: Vec::new(),
+            links: Vec::new(),
+            code: None,
+        };
+
+        for event in Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH) {
+            renderer.event(event);
+        }
+        renderer.flush();
+
+        renderer.out.trim_end().to_string()
+    }
+
+    /// Renders text that arrives in chunks, see [`MarkdownStream`].
+    pub fn stream(&self) -> MarkdownStream<'_> {
+        MarkdownStream {
+            markdown: self,
+            pending: String::new(),
+            started: false,
+        }
+    }
+}
+
+/// Renders a streamed response block by block, so that it can be shown as
+/// it arrives. Text is held back until the block it belongs to is complete:
+/// a blank line outside of a code block, followed by an unindented line.
+pub struct MarkdownStream<'a> {
+    markdown: &'a Markdown,
+    pending: String,
+    started: bool,
+}
+
+impl MarkdownStream<'_> {
+    /// Adds `chunk`, returning the blocks it completed, if any.
+    pub fn push(&mut self, chunk: &str) -> Option<String> {
+        self.pending.push_str(chunk);
+
+        let end = block_boundary(&self.pending)?;
+        let rest = self.pending.split_off(end);
+        let blocks = std::mem::replace(&mut self.pending, rest);
+
+        self.output(&blocks)
+    }
+
+    /// Renders what is left once the stream has ended.
+    pub fn finish(mut self) -> Option<String> {
+        let blocks = std::mem::take(&mut self.pending);
+        self.output(&blocks)
+    }
+
+    fn output(&mut self, blocks: &str) -> Option<String> {
+        let rendered = self.markdown.render(blocks);
+        if rendered.is_empty() {
+            return None;
+        }
+
+        // Blocks rendered together are separated by a blank line as well
+        let separator = if self.started { "\n" } else { "" };
+        self.started = true;
+        Some(format!("{separator}{rendered}\n"))
+    }
+}
+
+/// The end of the last complete block in `text`.
+fn block_boundary(text: &str) -> Option<usize> {
+    let mut boundary = None;
+    let mut in_fence = false;
+    let mut after_blank = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if !line.ends_with('\n') {
+            break;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            after_blank = None;
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            after_blank.get_or_insert(start);
+        } else {
+            if after_blank.is_some() && !line.starts_with([' ', '\t']) {
+                boundary = Some(start);
+            }
+            after_blank = None;
+        }
+    }
+
+    boundary
+}
+
+struct Renderer<'a> {
+    markdown: &'a Markdown,
+    /// The blocks rendered so far.
+    out: String,
+    /// The text of the paragraph, heading or list item being rendered.
+    inline: String,
+    /// The ANSI styles of the enclosing inline elements.
+    styles: Vec<&'static str>,
+    /// What every line of the current block starts with, one entry per
+    /// enclosing list item or quote.
+    indent: Vec<String>,
+    /// A list marker to put in place of `indent[index]` on the next line.
+    marker: Option<(usize, String)>,
+    /// The next number of each enclosing list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    /// The URL of each enclosing link, and where its text starts in `inline`.
+    links: Vec<(String, usize)>,
+    /// The language and text of the code block being rendered.
+    code: Option<(String, String)>,
+}
+
+impl Renderer<'_> {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => match &mut self.code {
+                Some((_, code)) => code.push_str(&text),
+                None => self.inline.push_str(&text),
+            },
+            Event::Code(code) => match self.markdown.color {
+                true => {
+                    self.push_style(CODE);
+                    self.inline.push_str(&code);
+                    self.pop_style();
+                }
+                false => self.inline.push_str(&format!("`{code}`")),
+            },
+            Event::Html(html) | Event::InlineHtml(html) => self.inline.push_str(&html),
+            Event::SoftBreak => self.inline.push(' '),
+            Event::HardBreak => self.inline.push('\n'),
+            Event::Rule => {
+                self.separate();
+                let prefix = self.take_prefix();
+                let width = self
+                    .markdown
+                    .width
+                    .saturating_sub(textwrap::core::display_width(&prefix));
+                let rule = match self.markdown.color {
+                    true => format!("\x1b[{DIM}m{}{RESET}", "─".repeat(width)),
+                    false => "-".repeat(width),
+                };
+                self.out.push_str(&format!("{prefix}{rule}\n"));
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::HtmlBlock => self.separate(),
+            Tag::Heading { level, .. } => {
+                self.separate();
+                match self.markdown.color {
+                    true => self.push_style(match level {
+                        HeadingLevel::H1 => TITLE,
+                        _ => HEADING,
+                    }),
+                    false => self
+                        .inline
+                        .push_str(&format!("{} ", "#".repeat(level as usize))),
+                }
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.separate();
+                self.indent.push(match self.markdown.color {
+                    true => format!("\x1b[{DIM}m│{RESET} "),
+                    false => "> ".to_string(),
+                });
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                self.separate();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.separate();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ if self.markdown.color => "• ".to_string(),
+                    _ => "- ".to_string(),
+                };
+                self.indent.push(" ".repeat(marker.chars().count()));
+                self.marker = Some((self.indent.len() - 1, marker));
+            }
+            Tag::Emphasis => self.push_style(ITALIC),
+            Tag::Strong => self.push_style(BOLD),
+            Tag::Strikethrough => self.push_style(STRIKETHROUGH),
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.push_style(LINK);
+                self.links.push((dest_url.to_string(), self.inline.len()));
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.flush(),
+            TagEnd::Heading(_) => {
+                self.pop_style();
+                self.flush();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.indent.pop();
+            }
+            TagEnd::CodeBlock => self.code_block(),
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.indent.pop();
+                self.marker = None;
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => self.pop_style(),
+            TagEnd::Link | TagEnd::Image => {
+                self.pop_style();
+                let Some((url, start)) = self.links.pop() else {
+                    return;
+                };
+
+                let text = strip_ansi_escapes::strip(&self.inline[start..]).unwrap_or_default();
+                if !url.is_empty() && text != url.as_bytes() && !url.starts_with('#') {
+                    match self.markdown.color {
+                        true => self.inline.push_str(&format!(" \x1b[{DIM}m({url}){RESET}")),
+                        false => self.inline.push_str(&format!(" ({url})")),
+                    }
+                    self.restore_styles();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Writes the pending text as a block, wrapped to the width.
+    fn flush(&mut self) {
+        if self.inline.trim().is_empty() {
+            self.inline.clear();
+            return;
+        }
+
+        let initial = self.take_prefix();
+        let subsequent = self.prefix();
+        let options = textwrap::Options::new(self.markdown.width)
+            .initial_indent(&initial)
+            .subsequent_indent(&subsequent)
+            .break_words(false);
+
+        for line in textwrap::wrap(self.inline.trim(), options) {
+            self.out.push_str(line.trim_end());
+            self.out.push('\n');
+        }
+        self.inline.clear();
+    }
+
+    fn code_block(&mut self) {
+        let Some((language, code)) = self.code.take() else {
+            return;
+        };
+
+        let lines: Vec<String> = match self.markdown.color {
+            true => highlight(&language, &code)
+                .into_iter()
+                .map(|line| format!("  {line}{RESET}"))
+                .collect(),
+            false => code.lines().map(|line| format!("    {line}")).collect(),
+        };
+
+        for line in lines {
+            let prefix = self.take_prefix();
+            self.out.push_str(format!("{prefix}{line}").trim_end());
+            self.out.push('\n');
+        }
+    }
+
+    /// Starts a block with a blank line, unless it is the first one.
+    fn separate(&mut self) {
+        if self.out.is_empty() || self.out.ends_with("\n\n") {
+            return;
+        }
+
+        let prefix = self.prefix();
+        self.out.push_str(prefix.trim_end());
+        self.out.push('\n');
+    }
+
+    /// The indentation of the lines of the current block after the first.
+    fn prefix(&self) -> String {
+        self.indent.concat()
+    }
+
+    /// The indentation of the next line, with the list marker that is still
+    /// to be written.
+    fn take_prefix(&mut self) -> String {
+        let Some((index, marker)) = self.marker.take() else {
+            return self.prefix();
+        };
+
+        self.indent
+            .iter()
+            .enumerate()
+            .map(|(i, indent)| if i == index { &marker } else { indent })
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn push_style(&mut self, style: &'static str) {
+        if self.markdown.color {
+            self.styles.push(style);
+            self.inline.push_str(&format!("\x1b[{style}m"));
+        }
+    }
+
+    fn pop_style(&mut self) {
+        if self.markdown.color {
+            self.styles.pop();
+            self.restore_styles();
+        }
+    }
+
+    /// Resets the style of the text, then applies the enclosing styles.
+    fn restore_styles(&mut self) {
+        if !self.markdown.color {
+            return;
+        }
+
+        self.inline.push_str(RESET);
+        for style in &self.styles {
+            self.inline.push_str(&format!("\x1b[{style}m"));
+        }
+    }
+}
+
+/// The lines of `code` with ANSI escapes for the syntax of `language`.
+fn highlight(language: &str, code: &str) -> Vec<String> {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme: &Theme = &THEME_SET.get_or_init(ThemeSet::load_defaults).themes[THEME];
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    LinesWithEndings::from(code)
+        .map(|line| match highlighter.highlight_line(line, syntaxes) {
+            Ok(ranges) => as_24_bit_terminal_escaped(&ranges, false),
+            Err(_) => line.to_string(),
+        })
+        .map(|line| line.trim_end_matches('\n').to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "# Title\n\nFirst line of the summary,\ncontinued here.\n\n\
+        - **bold** item\n- `code` item\n\n[docs](https://example.com)\n\n\
+        ```rust\nfn main() {}\n```\n";
+
+    fn plain(text: &str) -> String {
+        Markdown::new(false, 80).render(text)
+    }
+
+    #[test]
+    fn keeps_a_leading_list_marker() {
+        assert_eq!(plain("- item\n"), "- item");
+        assert_eq!(plain("- one\n- two\n"), "- one\n- two");
+    }
+
+    #[test]
+    fn keeps_the_first_line() {
+        let rendered = plain(DOCUMENT);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "# Title");
+        assert_eq!(lines[2], "First line of the summary, continued here.");
+        assert_eq!(lines[4], "- bold item");
+        assert_eq!(lines[5], "- `code` item");
+        assert_eq!(lines[7], "docs (https://example.com)");
+        assert_eq!(lines[9], "    fn main() {}");
+    }
+
+    #[test]
+    fn has_no_escapes_without_color() {
+        assert!(!plain(DOCUMENT).contains('\x1b'));
+        assert!(Markdown::new(true, 80).render(DOCUMENT).contains('\x1b'));
+
+        assert!(!use_color(ColorChoice::Never, true));
+        assert!(use_color(ColorChoice::Always, false));
+        assert!(!use_color(ColorChoice::Auto, false));
+    }
+
+    #[test]
+    fn no_color_turns_off_automatic_color() {
+        // The only test that reads NO_COLOR, as the variable is process-wide
+        std::env::set_var("NO_COLOR", "1");
+        let with_no_color = use_color(ColorChoice::Auto, true);
+        let always = use_color(ColorChoice::Always, true);
+        std::env::remove_var("NO_COLOR");
+
+        assert!(!with_no_color);
+        assert!(always);
+    }
+
+    #[test]
+    fn wraps_to_the_width() {
+        let text = "The quick brown fox jumps over the lazy dog and keeps running.\n\n\
+            - a list item that is long enough to wrap onto a second line\n";
+        let rendered = Markdown::new(false, 24).render(text);
+
+        for line in rendered.lines() {
+            assert!(line.chars().count() <= 24, "{line:?} is too long");
+        }
+        assert!(rendered.starts_with("The quick brown fox\n"));
+        // Wrapped list items are indented under their text
+        assert!(rendered.contains("- a list item that is\n  long enough"));
+    }
+
+    #[test]
+    fn block_boundary_never_splits_a_code_block() {
+        assert_eq!(block_boundary("```\nfirst\n\nsecond\n"), None);
+        assert_eq!(block_boundary("Intro\n\n~~~\nfirst\n\nsecond\n"), None);
+
+        let text = "Intro\n\n```\nfirst\n\nsecond\n```\n\nNext";
+        assert_eq!(block_boundary(text), None);
+        let text = "Intro\n\n```\nfirst\n\nsecond\n```\n\nNext\n";
+        assert_eq!(block_boundary(text), text.find("Next"));
+    }
+
+    #[test]
+    fn streams_complete_blocks() {
+        let markdown = Markdown::new(false, 80);
+        let mut stream = markdown.stream();
+
+        assert_eq!(stream.push("- first"), None);
+        assert_eq!(stream.push(" item\n\n```\nlet a = 1;\n\n"), None);
+        assert_eq!(stream.push("let b = 2;\n```\n\nDone"), None);
+        assert_eq!(
+            stream.push("\n\nMore\n"),
+            Some("- first item\n\n    let a = 1;\n\n    let b = 2;\n\nDone\n".to_string())
+        );
+        assert_eq!(stream.finish(), Some("\nMore\n".to_string()));
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::diff;
+
+#[derive(Debug, Clone)]
+pub enum PathFilterError {
+    InvalidPattern(String, String),
+}
+
+impl std::fmt::Display for PathFilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathFilterError::InvalidPattern(pattern, err) => {
+                write!(f, "Invalid path pattern '{pattern}': {err}")
+            }
+        }
+    }
+}
+
+/// Paths that are excluded unless an include pattern names them or a pattern
+/// starting with `!` re-includes them: lockfiles, minified assets and source
+/// maps, which are large and say little about the change.
+pub const DEFAULT_EXCLUDES: [&str; 14] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "composer.lock",
+    "Gemfile.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "go.sum",
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+];
+
+/// Name of the ignore file read from the repository root.
+const IGNORE_FILE: &str = ".lumenignore";
+
+/// Decides which files contribute to the diff sent to providers. Patterns
+/// follow `.gitignore` conventions: a pattern without a `/` matches a file or
+/// directory name at any depth, otherwise it is anchored at the repository
+/// root. An exclude pattern starting with `!` re-includes paths that other
+/// patterns exclude, and include patterns override [`DEFAULT_EXCLUDES`].
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    keep: GlobSet,
+    defaults: GlobSet,
+    /// The patterns the filter was built from, which identify it in cache
+    /// keys.
+    patterns: String,
+}
+
+impl PathFilter {
+    /// A filter that excludes the [`DEFAULT_EXCLUDES`] as well as `exclude`.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, PathFilterError> {
+        let patterns = format!("include: {include:?}\nexclude: {exclude:?}");
+        let (keep, exclude): (Vec<String>, Vec<String>) = exclude
+            .iter()
+            .cloned()
+            .partition(|pattern| pattern.trim_start().starts_with('!'));
+        let keep: Vec<String> = keep
+            .iter()
+            .map(|pattern| pattern.trim_start()[1..].to_string())
+            .collect();
+
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(include)?)
+        };
+
+        Ok(PathFilter {
+            include,
+            exclude: build_glob_set(&exclude)?,
+            keep: build_glob_set(&keep)?,
+            defaults: build_glob_set(&DEFAULT_EXCLUDES.map(String::from))?,
+            patterns,
+        })
+    }
+
+    /// The include and exclude patterns, one list per line.
+    pub fn patterns(&self) -> &str {
+        &self.patterns
+    }
+
+    /// Patterns from `.lumenignore` at the root of the repository, one per
+    /// line. Blank lines and lines starting with `#` are skipped.
+    pub fn read_ignore_file(repo_root: &Path) -> Result<Vec<String>, io::Error> {
+        let content = match fs::read_to_string(repo_root.join(IGNORE_FILE)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect())
+    }
+
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let included = match &self.include {
+            Some(include) if !include.is_match(path) => return true,
+            Some(_) => true,
+            None => false,
+        };
+
+        let excluded = self.exclude.is_match(path) || (!included && self.defaults.is_match(path));
+        excluded && !self.keep.is_match(path)
+    }
+
+    /// Removes the sections of excluded files from a diff, returning the
+    /// remaining diff and the paths that were left out.
+    pub fn apply(&self, diff: &str) -> (String, Vec<String>) {
+        let (preamble, files) = diff::split(diff);
+        let (omitted, files): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .partition(|file| self.is_excluded(&file.path));
+
+        (
+            diff::join(&preamble, &files),
+            omitted.into_iter().map(|file| file.path).collect(),
+        )
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, PathFilterError> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let trimmed = pattern.trim().trim_end_matches('/');
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let anchored = trimmed.contains('/');
+        let trimmed = trimmed.trim_start_matches('/');
+        let base = if anchored {
+            trimmed.to_string()
+        } else {
+            format!("**/{trimmed}")
+        };
+
+        // Match the path itself as well as everything below it when it
+        // names a directory
+        for glob in [base.clone(), format!("{base}/**")] {
+            builder.add(build_glob(pattern, &glob)?);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|err| PathFilterError::InvalidPattern(patterns.join(", "), err.to_string()))
+}
+
+fn build_glob(pattern: &str, glob: &str) -> Result<Glob, PathFilterError> {
+    GlobBuilder::new(glob)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| PathFilterError::InvalidPattern(pattern.to_string(), err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let strings =
+            |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        PathFilter::new(&strings(include), &strings(exclude)).unwrap()
+    }
+
+    #[test]
+    fn excludes_lockfiles_and_minified_assets_by_default() {
+        let filter = filter(&[], &[]);
+
+        assert!(filter.is_excluded("Cargo.lock"));
+        assert!(filter.is_excluded("web/package-lock.json"));
+        assert!(filter.is_excluded("static/js/app.min.js"));
+        assert!(filter.is_excluded("static/js/app.js.map"));
+        assert!(!filter.is_excluded("src/main.rs"));
+        assert!(!filter.is_excluded("static/js/app.js"));
+        assert!(!PathFilter::default().is_excluded("Cargo.lock"));
+    }
+
+    #[test]
+    fn includes_only_matching_paths() {
+        let filter = filter(&["src/**"], &[]);
+
+        assert!(!filter.is_excluded("src/main.rs"));
+        assert!(!filter.is_excluded("src/provider/mod.rs"));
+        assert!(filter.is_excluded("README.md"));
+        assert!(filter.is_excluded("tests/src/fixture.rs"));
+    }
+
+    #[test]
+    fn include_overrides_default_excludes_only() {
+        let filter = filter(&["Cargo.lock", "src/"], &["src/generated/"]);
+
+        assert!(!filter.is_excluded("Cargo.lock"));
+        assert!(!filter.is_excluded("src/lib.rs"));
+        assert!(filter.is_excluded("src/generated/schema.rs"));
+    }
+
+    #[test]
+    fn negated_patterns_re_include() {
+        let filter = filter(&[], &["!Cargo.lock", "docs/", "!docs/index.md"]);
+
+        assert!(!filter.is_excluded("Cargo.lock"));
+        assert!(filter.is_excluded("yarn.lock"));
+        assert!(filter.is_excluded("docs/guide.md"));
+        assert!(!filter.is_excluded("docs/index.md"));
+    }
+
+    #[test]
+    fn anchors_patterns_with_a_slash() {
+        let anchored = filter(&[], &["vendor/**"]);
+        assert!(anchored.is_excluded("vendor/lib.rs"));
+        assert!(anchored.is_excluded("vendor/a/b/c.rs"));
+        assert!(!anchored.is_excluded("third_party/vendor/lib.rs"));
+
+        let anywhere = filter(&[], &["vendor/", "*.snap"]);
+        assert!(anywhere.is_excluded("vendor/a/b/c.rs"));
+        assert!(anywhere.is_excluded("third_party/vendor/lib.rs"));
+        assert!(anywhere.is_excluded("tests/snapshots/output.snap"));
+        assert!(!anywhere.is_excluded("src/vendored.rs"));
+    }
+
+    #[test]
+    fn removes_excluded_files_from_a_diff() {
+        let diff = "diff --git a/Cargo.lock b/Cargo.lock\n--- a/Cargo.lock\n+++ b/Cargo.lock\n\
+            @@ -1 +1 @@\n-old\n+new\n\
+            diff --git a/src/main.rs b/src/main.rs\n--- a/src/main.rs\n+++ b/src/main.rs\n\
+            @@ -1 +1 @@\n-fn old() {}\n+fn new() {}\n";
+
+        let (kept, omitted) = filter(&[], &[]).apply(diff);
+
+        assert_eq!(omitted, ["Cargo.lock"]);
+        assert!(kept.starts_with("diff --git a/src/main.rs"));
+        assert!(!kept.contains("Cargo.lock"));
+    }
+
+    #[test]
+    fn reads_the_ignore_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(PathFilter::read_ignore_file(dir.path()).unwrap().is_empty());
+
+        fs::write(
+            dir.path().join(IGNORE_FILE),
+            "# generated code\n\n  gen/  \n!Cargo.lock\n",
+        )
+        .unwrap();
+        assert_eq!(
+            PathFilter::read_ignore_file(dir.path()).unwrap(),
+            ["gen/", "!Cargo.lock"]
+        );
+    }
+}
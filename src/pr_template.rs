@@ -0,0 +1,123 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Locations checked for a repository's pull request template, relative to
+/// the repository root.
+const TEMPLATE_PATHS: [&str; 6] = [
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+];
+
+const DEFAULT_TEMPLATE: &str = "## Summary
+
+## Motivation
+
+## Changes
+
+## Testing
+";
+
+/// The structure a generated pull request description has to follow.
+#[derive(Clone, Debug)]
+pub struct PrTemplate {
+    pub content: String,
+    pub headings: Vec<String>,
+}
+
+impl PrTemplate {
+    /// Reads `path` if given, otherwise the first template found in the
+    /// repository, otherwise the built-in Summary / Motivation / Changes /
+    /// Testing layout.
+    pub fn load(path: Option<&Path>, repo_root: Option<&Path>) -> Result<Self, io::Error> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => repo_root.and_then(Self::find),
+        };
+
+        let content = match path {
+            Some(path) => fs::read_to_string(path)?,
+            None => DEFAULT_TEMPLATE.to_string(),
+        };
+
+        Ok(PrTemplate {
+            headings: headings(&content),
+            content,
+        })
+    }
+
+    fn find(repo_root: &Path) -> Option<PathBuf> {
+        TEMPLATE_PATHS
+            .iter()
+            .map(|path| repo_root.join(path))
+            .find(|path| path.is_file())
+    }
+
+    /// The template headings that do not appear, in order, in `description`.
+    pub fn missing_headings(&self, description: &str) -> Vec<String> {
+        let found: Vec<String> = headings(description)
+            .iter()
+            .map(|heading| normalize(heading))
+            .collect();
+
+        let mut position = 0;
+        let mut missing = Vec::new();
+        for heading in &self.headings {
+            match found[position..]
+                .iter()
+                .position(|found| *found == normalize(heading))
+            {
+                Some(offset) => position += offset + 1,
+                None => missing.push(heading.clone()),
+            }
+        }
+
+        missing
+    }
+}
+
+/// Markdown ATX headings outside of code blocks and HTML comments, e.g.
+/// `## Testing`.
+fn headings(markdown: &str) -> Vec<String> {
+    let mut headings = Vec::new();
+    let mut in_code = false;
+    let mut in_comment = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if trimmed.starts_with("<!--") {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+        if in_comment {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+
+        if !in_code && trimmed.starts_with('#') {
+            let heading = trimmed.trim_start_matches('#');
+            if heading.starts_with(' ') {
+                headings.push(trimmed.to_string());
+            }
+        }
+    }
+
+    headings
+}
+
+fn normalize(heading: &str) -> String {
+    heading
+        .trim_start_matches('#')
+        .trim()
+        .trim_end_matches(':')
+        .to_lowercase()
+}
@@ -0,0 +1,376 @@
+use crate::{ai_prompt::AIPrompt, config::LumenConfig};
+
+const COMMIT_SYSTEM: &str = "You are a helpful assistant that analyzes git commits. \
+    Provide a concise summary of the changes based on the commit message and diff content. \
+    Focus on the impact and purpose of the changes.";
+
+const DIFF_SYSTEM: &str = "You are a helpful assistant that analyzes git diffs. \
+    Summarize changes accurately and concisely, without speculating about code you cannot see.";
+
+/// A built-in template, and the variables it can be rendered with.
+struct Builtin {
+    name: &'static str,
+    system: &'static str,
+    user: &'static str,
+    variables: &'static [&'static str],
+    /// Variables a template must use to make sense, e.g. the diff to
+    /// summarize.
+    required: &'static [&'static str],
+}
+
+const BUILTINS: [Builtin; 9] = [
+    Builtin {
+        name: "explain",
+        system: COMMIT_SYSTEM,
+        user: "Please analyze this git commit and provide a summary.\n\n\
+               Commit Message:\n{{message}}\n\n\
+               Diff Content:\n{{diff}}{{omitted}}",
+        variables: &[
+            "hash", "author", "date", "message", "branch", "files", "diff", "omitted",
+        ],
+        required: &["diff"],
+    },
+    Builtin {
+        name: "suggest",
+        system: COMMIT_SYSTEM,
+        user: "Please analyze the following staged changes and provide a short, concise title \
+               and a detailed summary.\n\n\
+               Diff Content:\n{{diff}}{{omitted}}",
+        variables: &["branch", "files", "diff", "omitted"],
+        required: &["diff"],
+    },
+    Builtin {
+        name: "range",
+        system: COMMIT_SYSTEM,
+        user: "Please analyze this range of git commits and provide a single summary of the \
+               changes it introduces as a whole, rather than a summary per commit.\n\n\
+               Commits:\n{{commits}}\n\n\
+               Diff Content:\n{{diff}}{{omitted}}",
+        variables: &["range", "commits", "branch", "files", "diff", "omitted"],
+        required: &["diff"],
+    },
+    Builtin {
+        name: "commit",
+        system: "You are a helpful assistant that writes git commit messages. \
+                 Describe what the staged changes do and why, in the imperative mood.",
+        user: "Write a commit message for the following staged changes.\n\n\
+               {{format}}\n\n\
+               Respond with the commit message only, without code fences or commentary.\n\n\
+               Diff Content:\n{{diff}}{{omitted}}",
+        variables: &["format", "branch", "files", "diff", "omitted"],
+        required: &["diff"],
+    },
+    Builtin {
+        name: "pr",
+        system: "You are a helpful assistant that writes pull request descriptions. \
+                 Explain what the changes do and why, for a reviewer who has not seen them yet.",
+        user: "Write a pull request description in Markdown for the following branch.\n\n\
+               Fill in this template, keeping every heading in the same order. \
+               Under a Changes heading, list the changes as bullet points; \
+               under a Testing heading, describe how the changes can be verified:\n\n\
+               {{template}}\n\n\
+               Respond with the Markdown description only, without code fences or commentary.\n\n\
+               Branch: {{branch}} (into {{base}})\n\n\
+               Commits:\n{{commits}}\n\n\
+               Diff Content:\n{{diff}}{{omitted}}",
+        variables: &[
+            "template", "branch", "base", "commits", "files", "diff", "omitted",
+        ],
+        required: &["template", "diff"],
+    },
+    Builtin {
+        name: "changelog",
+        system: "You are a helpful assistant that maintains changelogs. \
+                 Classify commits by their effect on the users of the project.",
+        user: "Classify the following commits for a changelog.\n\n\
+               Answer with one line per commit in the form `<number>: <category>`, \
+               where category is one of {{categories}}. \
+               Use breaking for changes that break backwards compatibility, added for new features, \
+               fixed for bug fixes, internal for changes that do not affect users \
+               (documentation, tests, refactoring, build and CI) and changed for anything else.\n\n\
+               Respond with the classification only, without code fences or commentary.\n\n\
+               Commits:\n{{commits}}",
+        variables: &["categories", "commits"],
+        required: &["commits"],
+    },
+    Builtin {
+        name: "chunk",
+        system: DIFF_SYSTEM,
+        user: "Please summarize the following part of a larger diff.\n\n\
+               This is part {{part}} of {{parts}} of the diff. \
+               List every file it touches and what changed in it.\n\n\
+               Diff Content:\n{{diff}}",
+        variables: &["part", "parts", "diff"],
+        required: &["diff"],
+    },
+    Builtin {
+        name: "merge",
+        system: DIFF_SYSTEM,
+        user: "Please merge the following summaries of consecutive parts of one diff into a \
+               single summary.\n\n\
+               Keep every file and change they mention, but remove repetition.\n\n\
+               Summaries:\n{{summaries}}",
+        variables: &["summaries"],
+        required: &["summaries"],
+    },
+    Builtin {
+        name: "chat",
+        system: "You are a helpful assistant that answers questions about git changes. \
+                 Base your answers on the commit messages and the diff, \
+                 and say so when they do not answer a question.",
+        user: "Answer questions about the following changes.\n\n\
+               {{changes}}\n\n\
+               Diff Content:\n{{diff}}{{omitted}}\n\n\
+               Question: {{question}}",
+        variables: &["changes", "branch", "files", "diff", "omitted", "question"],
+        required: &["diff", "question"],
+    },
+];
+
+#[derive(Debug, Clone)]
+pub enum PromptTemplateError {
+    UnknownTemplate(String),
+    UnknownVariable(String, String, Vec<String>),
+    MissingVariable(String, String),
+    RangeRequired(String),
+}
+
+impl std::fmt::Display for PromptTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PromptTemplateError::UnknownTemplate(name) => write!(
+                f,
+                "Unknown prompt template '{name}' (available: {})",
+                names().join(", ")
+            ),
+            PromptTemplateError::UnknownVariable(name, variable, available) => write!(
+                f,
+                "Prompt template '{name}' uses unknown variable {{{{{variable}}}}} (available: {})",
+                available.join(", ")
+            ),
+            PromptTemplateError::MissingVariable(name, variable) => write!(
+                f,
+                "Prompt template '{name}' must use {{{{{variable}}}}}"
+            ),
+            PromptTemplateError::RangeRequired(name) => write!(
+                f,
+                "Showing the '{name}' prompt requires a range (e.g. `lumen prompt show {name} main..HEAD`)"
+            ),
+        }
+    }
+}
+
+/// A system prompt and user prompt with `{{variable}}` placeholders.
+#[derive(Clone, Debug)]
+pub struct PromptTemplate {
+    pub system: String,
+    pub user: String,
+}
+
+impl PromptTemplate {
+    /// The built-in template `name`, with the parts overridden by
+    /// `[prompts.<name>]` in the config.
+    pub fn get(name: &str, config: &LumenConfig) -> Result<Self, PromptTemplateError> {
+        let builtin = builtin(name)?;
+
+        Ok(PromptTemplate {
+            system: config
+                .prompt(name, "system")
+                .unwrap_or(builtin.system)
+                .to_string(),
+            user: config
+                .prompt(name, "user")
+                .unwrap_or(builtin.user)
+                .to_string(),
+        })
+    }
+
+    /// Whether either part of the template uses `variable`.
+    pub fn uses(&self, variable: &str) -> bool {
+        [&self.system, &self.user]
+            .iter()
+            .any(|part| placeholders(part).any(|name| name == variable))
+    }
+
+    /// Replaces every placeholder with the value of its variable.
+    pub fn render(&self, variables: &[(&str, String)]) -> AIPrompt {
+        AIPrompt {
+            system_prompt: render(&self.system, variables),
+            user_prompt: render(&self.user, variables),
+            history: Vec::new(),
+        }
+    }
+}
+
+/// The names of the built-in templates.
+pub fn names() -> Vec<&'static str> {
+    BUILTINS.iter().map(|builtin| builtin.name).collect()
+}
+
+/// Checks the templates overridden in the config, so that a typo in a
+/// variable name is reported rather than sent to the provider.
+pub fn validate(config: &LumenConfig) -> Result<(), PromptTemplateError> {
+    for key in config.prompts.keys() {
+        let name = key.split_once('.').map_or(key.as_str(), |(name, _)| name);
+        check(name, &PromptTemplate::get(name, config)?)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that `template` only uses the variables of the built-in template
+/// `name`, and all of the ones it requires.
+fn check(name: &str, template: &PromptTemplate) -> Result<(), PromptTemplateError> {
+    let builtin = builtin(name)?;
+
+    for part in [&template.system, &template.user] {
+        if let Some(variable) =
+            placeholders(part).find(|variable| !builtin.variables.contains(variable))
+        {
+            return Err(PromptTemplateError::UnknownVariable(
+                name.to_string(),
+                variable.to_string(),
+                builtin.variables.iter().map(|v| v.to_string()).collect(),
+            ));
+        }
+    }
+
+    if let Some(variable) = builtin
+        .required
+        .iter()
+        .find(|variable| !template.uses(variable))
+    {
+        return Err(PromptTemplateError::MissingVariable(
+            name.to_string(),
+            variable.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn builtin(name: &str) -> Result<&'static Builtin, PromptTemplateError> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .ok_or_else(|| PromptTemplateError::UnknownTemplate(name.to_string()))
+}
+
+/// The variable names of the `{{ name }}` placeholders in `text`.
+fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.split("{{")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("}}"))
+        .map(|(name, _)| name.trim())
+}
+
+fn render(text: &str, variables: &[(&str, String)]) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+
+        rendered.push_str(&rest[..start]);
+        match variables.iter().find(|(variable, _)| *variable == name) {
+            Some((_, value)) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(system: &str, user: &str) -> PromptTemplate {
+        PromptTemplate {
+            system: system.to_string(),
+            user: user.to_string(),
+        }
+    }
+
+    #[test]
+    fn render_substitutes_variables() {
+        let prompt = template("Summarize {{hash}}.", "{{message}}\n\n{{ diff }}").render(&[
+            ("hash", "1a2b3c4".to_string()),
+            ("message", "Fix the parser".to_string()),
+            ("diff", "+fixed".to_string()),
+        ]);
+
+        assert_eq!(prompt.system_prompt, "Summarize 1a2b3c4.");
+        assert_eq!(prompt.user_prompt, "Fix the parser\n\n+fixed");
+        assert!(prompt.history.is_empty());
+    }
+
+    #[test]
+    fn render_substitutes_repeated_variables() {
+        let prompt = template("", "{{part}} of {{parts}}, then {{part}}")
+            .render(&[("part", "2".to_string()), ("parts", "3".to_string())]);
+
+        assert_eq!(prompt.user_prompt, "2 of 3, then 2");
+    }
+
+    #[test]
+    fn render_keeps_unknown_placeholders() {
+        let prompt =
+            template("", "{{diff}} {{other}} {{unclosed").render(&[("diff", "+added".to_string())]);
+
+        assert_eq!(prompt.user_prompt, "+added {{other}} {{unclosed");
+    }
+
+    #[test]
+    fn render_does_not_substitute_inside_values() {
+        // Diffs of templates contain placeholders of their own
+        let prompt = template("", "{{diff}}{{omitted}}").render(&[
+            ("diff", "+{{omitted}}".to_string()),
+            ("omitted", " (and more)".to_string()),
+        ]);
+
+        assert_eq!(prompt.user_prompt, "+{{omitted}} (and more)");
+    }
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        for builtin in &BUILTINS {
+            let template = template(builtin.system, builtin.user);
+            assert!(check(builtin.name, &template).is_ok(), "{}", builtin.name);
+        }
+    }
+
+    #[test]
+    fn check_rejects_unknown_variables() {
+        let err = check("explain", &template("", "{{diff}} by {{auther}}")).unwrap_err();
+
+        assert!(matches!(
+            &err,
+            PromptTemplateError::UnknownVariable(name, variable, available)
+                if name == "explain" && variable == "auther" && available.contains(&"author".to_string())
+        ));
+        assert!(err.to_string().contains("unknown variable {{auther}}"));
+    }
+
+    #[test]
+    fn check_rejects_missing_required_variables() {
+        assert!(matches!(
+            check("chat", &template("", "{{diff}}")),
+            Err(PromptTemplateError::MissingVariable(name, variable))
+                if name == "chat" && variable == "question"
+        ));
+    }
+
+    #[test]
+    fn check_rejects_unknown_templates() {
+        assert!(matches!(
+            check("explian", &template("", "{{diff}}")),
+            Err(PromptTemplateError::UnknownTemplate(name)) if name == "explian"
+        ));
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::dry_run::DryRun;
+use super::error::ProviderError;
+use super::{AIProvider, Completion, LumenProvider, TextStream};
+use crate::{ai_prompt::AIPrompt, config::LumenConfig, error::LumenError};
+
+/// The delay before the first retry; it doubles with every further retry.
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Longer `Retry-After` waits are not worth sitting through; the next
+/// provider in the chain is tried instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// A completion, and which provider in the chain gave it.
+pub struct Answer {
+    pub provider: &'static str,
+    pub model: String,
+    pub completion: Completion,
+}
+
+/// A completion being streamed, and which provider in the chain gives it.
+pub struct StreamedAnswer {
+    pub provider: &'static str,
+    pub model: String,
+    pub stream: TextStream,
+}
+
+/// The configured provider followed by its fallbacks. Each one is retried
+/// with exponential backoff on transient failures, and the next one is tried
+/// when it stays unavailable.
+pub struct ProviderChain {
+    providers: Vec<LumenProvider>,
+    retries: u32,
+    dry_run: bool,
+    /// The configured API key and header values, redacted from dry runs.
+    secrets: Vec<String>,
+}
+
+impl ProviderChain {
+    pub fn new(client: reqwest::Client, config: &LumenConfig) -> Result<Self, LumenError> {
+        let mut providers = vec![LumenProvider::new(client.clone(), config)?];
+        for fallback in &config.fallback.value {
+            let config = config.for_fallback(*fallback);
+            providers.push(LumenProvider::new(client.clone(), &config)?);
+        }
+
+        let secrets = config
+            .api_key
+            .value
+            .iter()
+            .chain(config.headers.values().map(|setting| &setting.value))
+            .cloned()
+            .collect();
+
+        Ok(ProviderChain {
+            providers,
+            retries: config.retries.value,
+            dry_run: false,
+            secrets,
+        })
+    }
+
+    /// Marks the chain as a dry run, whose requests are shown with
+    /// [`ProviderChain::dry_run`] instead of being sent.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Completes `prompt` with output following the JSON `schema`, where the
+    /// provider that answers supports structured output.
+    pub async fn complete_json(
+        &self,
+        prompt: AIPrompt,
+        schema: &serde_json::Value,
+    ) -> Result<Answer, Box<dyn std::error::Error>> {
+        self.answer_with(prompt, Some(schema)).await
+    }
+
+    /// Completes `prompt` like [`AIProvider::complete`], along with which
+    /// provider answered.
+    pub async fn answer(&self, prompt: AIPrompt) -> Result<Answer, Box<dyn std::error::Error>> {
+        self.answer_with(prompt, None).await
+    }
+
+    /// Streams the completion of `prompt` like
+    /// [`AIProvider::complete_stream`], along with which provider answers.
+    /// Only opening the stream is retried; text that was already shown
+    /// cannot be taken back.
+    pub async fn answer_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<StreamedAnswer, Box<dyn std::error::Error>> {
+        let prompt = &prompt;
+        self.first_available(|provider| async move {
+            let stream = provider.complete_stream(prompt.clone()).await?;
+            Ok(StreamedAnswer {
+                provider: provider.name(),
+                model: provider.model().to_string(),
+                stream,
+            })
+        })
+        .await
+    }
+
+    async fn answer_with(
+        &self,
+        prompt: AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Answer, Box<dyn std::error::Error>> {
+        let prompt = &prompt;
+        self.first_available(|provider| async move {
+            let completion = provider.completion(prompt, schema).await?;
+            Ok(Answer {
+                provider: provider.name(),
+                model: provider.model().to_string(),
+                completion,
+            })
+        })
+        .await
+    }
+
+    /// The requests completing `prompt` sends, one per provider in the
+    /// chain. The fallbacks are only sent to when the providers before them
+    /// are unavailable.
+    pub fn dry_run(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Vec<DryRun>, reqwest::Error> {
+        self.providers
+            .iter()
+            .map(|provider| {
+                let request = provider.request(prompt, stream, schema).build()?;
+                Ok(DryRun::new(provider, &request, prompt, &self.secrets))
+            })
+            .collect()
+    }
+
+    /// The name and model of every provider in the chain, in the order they
+    /// are asked.
+    pub fn answerers(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.providers
+            .iter()
+            .map(|provider| (provider.name(), provider.model()))
+    }
+
+    /// The smallest context window in the chain, so that a prompt sized for
+    /// it fits whichever provider ends up answering.
+    pub fn context_window(&self, config: &LumenConfig) -> u32 {
+        self.providers
+            .iter()
+            .map(|provider| provider.context_window(config))
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// The models offered by the configured provider.
+    pub async fn list_models(&self) -> Result<Vec<String>, LumenError> {
+        self.providers[0].list_models().await
+    }
+
+    /// Sends `request` to each provider in turn until one of them succeeds
+    /// or fails for a reason other than being unavailable.
+    async fn first_available<'a, T, F, Fut>(
+        &'a self,
+        request: F,
+    ) -> Result<T, Box<dyn std::error::Error>>
+    where
+        F: Fn(&'a LumenProvider) -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+    {
+        assert!(!self.dry_run, "requests of dry runs are shown, not sent");
+
+        // The last provider's error is the one reported
+        let (last, others) = self
+            .providers
+            .split_last()
+            .expect("the chain starts with the configured provider");
+
+        for provider in others {
+            match self.with_retries(|| request(provider)).await {
+                Ok(value) => return Ok(value),
+                Err(err) => match err.downcast::<ProviderError>() {
+                    Ok(err) if err.is_unavailable() => continue,
+                    Ok(err) => return Err(err),
+                    Err(err) => return Err(err),
+                },
+            }
+        }
+
+        self.with_retries(|| request(last)).await
+    }
+
+    /// Calls `request` until it succeeds, fails with an error that is not
+    /// transient, or runs out of retries.
+    async fn with_retries<T, F, Fut>(&self, request: F) -> Result<T, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let delay = match request().await {
+                Ok(value) => return Ok(value),
+                Err(err) => match err.downcast::<ProviderError>() {
+                    Ok(err) if attempt < self.retries && err.is_transient() => {
+                        match retry_delay(&err, attempt) {
+                            Some(delay) => delay,
+                            None => return Err(err),
+                        }
+                    }
+                    Ok(err) => return Err(err),
+                    Err(err) => return Err(err),
+                },
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// How long to wait before retrying after `err`: what the provider asked
+/// for in `Retry-After`, or else exponential backoff with jitter so that
+/// concurrent clients do not retry in lockstep.
+fn retry_delay(err: &ProviderError, attempt: u32) -> Option<Duration> {
+    if let ProviderError::RateLimited(_, Some(seconds), _) = err {
+        let delay = Duration::from_secs(*seconds);
+        return (delay <= MAX_RETRY_AFTER).then_some(delay);
+    }
+
+    let backoff = BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_DELAY);
+
+    Some(backoff / 2 + backoff.mul_f64(random_fraction() / 2.0))
+}
+
+/// A random number in `[0, 1)`, from the randomly seeded hasher of the
+/// standard library.
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[async_trait]
+impl AIProvider for ProviderChain {
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        self.first_available(|provider| provider.complete(prompt.clone()))
+            .await
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
+        Ok(self.answer_stream(prompt).await?.stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::provider::ollama::OllamaProvider;
+    use crate::provider::phind::PhindProvider;
+
+    fn ollama(client: reqwest::Client, base_url: Option<String>) -> LumenProvider {
+        LumenProvider::Ollama(Box::new(OllamaProvider::new(
+            client, base_url, None, None, 8_192,
+        )))
+    }
+
+    /// Ollama followed by Phind as its fallback.
+    fn chain(retries: u32) -> ProviderChain {
+        let client = reqwest::Client::new();
+        ProviderChain {
+            providers: vec![
+                ollama(client.clone(), None),
+                LumenProvider::Phind(Box::new(PhindProvider::new(client, None))),
+            ],
+            retries,
+            dry_run: false,
+            secrets: Vec::new(),
+        }
+    }
+
+    fn rate_limited() -> ProviderError {
+        ProviderError::RateLimited("Ollama".to_string(), Some(0), "slow down".to_string())
+    }
+
+    fn auth_failed() -> ProviderError {
+        ProviderError::AuthFailed("Ollama".to_string(), "bad key".to_string())
+    }
+
+    fn server_error(provider: &str) -> ProviderError {
+        ProviderError::ServerError(provider.to_string(), 503, "down".to_string())
+    }
+
+    #[test]
+    fn waits_as_long_as_retry_after_asks_up_to_a_limit() {
+        let limited = |seconds| ProviderError::RateLimited("Groq".into(), Some(seconds), "".into());
+
+        assert_eq!(retry_delay(&limited(5), 0), Some(Duration::from_secs(5)));
+        assert_eq!(retry_delay(&limited(60), 3), Some(MAX_RETRY_AFTER));
+        assert_eq!(retry_delay(&limited(61), 0), None);
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        for attempt in 0..40 {
+            let delay = retry_delay(&server_error("Groq"), attempt).unwrap();
+            let backoff = BASE_DELAY
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(MAX_DELAY);
+            assert!(delay >= backoff / 2, "{delay:?} at attempt {attempt}");
+            assert!(delay <= MAX_DELAY, "{delay:?} at attempt {attempt}");
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_until_out_of_retries() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = chain(2)
+            .with_retries(|| async {
+                calls.set(calls.get() + 1);
+                Err(rate_limited().into())
+            })
+            .await;
+
+        assert_eq!(calls.get(), 3);
+        let err = result.unwrap_err().downcast::<ProviderError>().unwrap();
+        assert!(matches!(*err, ProviderError::RateLimited(..)));
+    }
+
+    #[tokio::test]
+    async fn returns_once_a_retry_succeeds() {
+        let calls = Cell::new(0);
+        let result = chain(2)
+            .with_retries(|| async {
+                calls.set(calls.get() + 1);
+                match calls.get() {
+                    1 => Err(rate_limited().into()),
+                    _ => Ok("summary"),
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), "summary");
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_errors() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = chain(2)
+            .with_retries(|| async {
+                calls.set(calls.get() + 1);
+                Err(auth_failed().into())
+            })
+            .await;
+
+        assert_eq!(calls.get(), 1);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_a_provider_is_unavailable() {
+        let answer = chain(0)
+            .first_available(|provider| async move {
+                match provider.name() {
+                    "ollama" => Err(server_error("Ollama").into()),
+                    name => Ok(name),
+                }
+            })
+            .await;
+
+        assert_eq!(answer.unwrap(), "phind");
+    }
+
+    #[tokio::test]
+    async fn does_not_fall_back_on_other_errors() {
+        let asked = Cell::new(Vec::new());
+        let result: Result<(), _> = chain(0)
+            .first_available(|provider| {
+                let mut providers = asked.take();
+                providers.push(provider.name());
+                asked.set(providers);
+                async { Err(auth_failed().into()) }
+            })
+            .await;
+
+        assert_eq!(asked.take(), ["ollama"]);
+        let err = result.unwrap_err().downcast::<ProviderError>().unwrap();
+        assert!(matches!(*err, ProviderError::AuthFailed(..)));
+    }
+
+    #[tokio::test]
+    async fn reports_the_last_providers_error() {
+        let result: Result<(), _> = chain(0)
+            .first_available(|provider| async move {
+                match provider.name() {
+                    "ollama" => Err(server_error("Ollama").into()),
+                    _ => Err(server_error("Phind").into()),
+                }
+            })
+            .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), server_error("Phind").to_string());
+    }
+
+    #[tokio::test]
+    async fn a_stalled_provider_times_out_as_a_transient_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        // Accepts the connection and never answers
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let provider = ollama(client, Some(format!("http://{address}")));
+        let prompt = AIPrompt {
+            system_prompt: String::new(),
+            user_prompt: "Summarize".to_string(),
+            history: Vec::new(),
+        };
+
+        let Err(err) = provider.completion(&prompt, None).await else {
+            panic!("a provider that never answers gave a completion");
+        };
+        assert!(matches!(err, ProviderError::NetworkError(..)), "{err}");
+        assert!(err.is_transient());
+    }
+}
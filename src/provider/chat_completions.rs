@@ -0,0 +1,337 @@
+use crate::ai_prompt::AIPrompt;
+
+use super::error::{self, ProviderError};
+use super::{conversation, stream, AIProvider, Completion, TextStream, Usage};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use serde_json::json;
+
+/// The services that implement the OpenAI chat completions API.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Vendor {
+    OpenAI,
+    Groq,
+    Mistral,
+    DeepSeek,
+    Compatible,
+}
+
+impl Vendor {
+    pub fn name(self) -> &'static str {
+        match self {
+            Vendor::OpenAI => "OpenAI",
+            Vendor::Groq => "Groq",
+            Vendor::Mistral => "Mistral",
+            Vendor::DeepSeek => "DeepSeek",
+            Vendor::Compatible => "openai-compatible",
+        }
+    }
+
+    pub fn base_url(self) -> Option<&'static str> {
+        match self {
+            Vendor::OpenAI => Some("https://api.openai.com/v1"),
+            Vendor::Groq => Some("https://api.groq.com/openai/v1"),
+            Vendor::Mistral => Some("https://api.mistral.ai/v1"),
+            Vendor::DeepSeek => Some("https://api.deepseek.com/v1"),
+            Vendor::Compatible => None,
+        }
+    }
+
+    pub fn default_model(self) -> Option<&'static str> {
+        match self {
+            Vendor::OpenAI => Some("gpt-4o-mini"),
+            Vendor::Groq => Some("mixtral-8x7b-32768"),
+            Vendor::Mistral => Some("mistral-small-latest"),
+            Vendor::DeepSeek => Some("deepseek-chat"),
+            Vendor::Compatible => None,
+        }
+    }
+
+    /// How to ask for output following `schema`: OpenAI enforces the
+    /// schema itself, the other services only guarantee valid JSON, and
+    /// servers implementing the API differ too much to ask for either.
+    fn response_format(self, schema: &serde_json::Value) -> Option<serde_json::Value> {
+        match self {
+            Vendor::OpenAI => Some(json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "strict": true, "schema": schema },
+            })),
+            Vendor::Groq | Vendor::Mistral | Vendor::DeepSeek => {
+                Some(json!({ "type": "json_object" }))
+            }
+            Vendor::Compatible => None,
+        }
+    }
+
+    /// Local servers usually accept requests without a key.
+    pub fn requires_api_key(self) -> bool {
+        self != Vendor::Compatible
+    }
+
+    /// Extracts the message from an error response body. Mistral reports
+    /// `{"message": ...}`, where the message may be a validation object;
+    /// everyone else follows OpenAI's `{"error": {"message": ...}}`.
+    fn error_message(self, body: &str) -> Option<String> {
+        if let Some(message) = error::error_message(body) {
+            return Some(message);
+        }
+
+        let body: serde_json::Value = serde_json::from_str(body).ok()?;
+        match (self, &body["message"]) {
+            (Vendor::Mistral, serde_json::Value::Object(message)) => {
+                let details: Vec<String> = message
+                    .get("detail")
+                    .and_then(|detail| detail.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|detail| {
+                        let msg = detail["msg"].as_str()?;
+                        let location = detail["loc"]
+                            .as_array()
+                            .map(|loc| {
+                                loc.iter()
+                                    .map(|part| part.to_string().trim_matches('"').to_string())
+                                    .collect::<Vec<_>>()
+                                    .join(".")
+                            })
+                            .unwrap_or_default();
+                        Some(format!("{location}: {msg}"))
+                    })
+                    .collect();
+                Some(details.join("; "))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A client for the OpenAI chat completions API, which OpenAI, Groq,
+/// Mistral, DeepSeek and most self-hosted servers and gateways implement.
+pub struct ChatCompletionsProvider {
+    client: reqwest::Client,
+    vendor: Vendor,
+    url: String,
+    headers: HeaderMap,
+    model: String,
+    max_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionsResponse {
+    choices: Vec<Choice>,
+    usage: Option<ChatCompletionsUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionsUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: Option<String>,
+    refusal: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionsStreamResponse {
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: Delta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+impl ChatCompletionsProvider {
+    /// `base_url` is the URL the API's paths are relative to, e.g.
+    /// `https://api.openai.com/v1`. A query string is kept after the path,
+    /// as Azure OpenAI requires for its `api-version` parameter.
+    pub fn new(
+        client: reqwest::Client,
+        vendor: Vendor,
+        base_url: &str,
+        headers: HeaderMap,
+        model: String,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        let url = match base_url.split_once('?') {
+            Some((base, query)) => {
+                format!("{}/chat/completions?{query}", base.trim_end_matches('/'))
+            }
+            None => format!("{}/chat/completions", base_url.trim_end_matches('/')),
+        };
+
+        ChatCompletionsProvider {
+            client,
+            vendor,
+            url,
+            headers,
+            model,
+            max_tokens,
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn create_payload(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> serde_json::Value {
+        let mut messages = vec![json!({
+            "role": "system",
+            "content": prompt.system_prompt,
+        })];
+        messages.extend(conversation(prompt));
+
+        let mut payload = json!({
+            "model": self.model,
+            "stream": stream,
+            "messages": messages,
+        });
+
+        if let Some(max_tokens) = self.max_tokens {
+            payload["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(format) = schema.and_then(|schema| self.vendor.response_format(schema)) {
+            payload["response_format"] = format;
+        }
+
+        payload
+    }
+
+    pub fn request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> reqwest::RequestBuilder {
+        self.client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .json(&self.create_payload(prompt, stream, schema))
+    }
+
+    async fn send_request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let name = self.vendor.name();
+        let response = self
+            .request(prompt, stream, schema)
+            .send()
+            .await
+            .map_err(|err| ProviderError::from_request(name, err))?;
+
+        error::check_response(name, response, |body| self.vendor.error_message(body)).await
+    }
+
+    pub async fn completion(
+        &self,
+        prompt: &AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Completion, ProviderError> {
+        let response = match self.send_request(prompt, false, schema).await {
+            // Older models reject structured output; the answer is then
+            // parsed from plain text
+            Err(ProviderError::RequestFailed(_, 400, message))
+                if schema.is_some() && message.contains("response_format") =>
+            {
+                self.send_request(prompt, false, None).await?
+            }
+            response => response?,
+        };
+
+        let name = self.vendor.name();
+        let response: ChatCompletionsResponse = error::parse_json(name, response).await?;
+        let usage = response.usage.map(|usage| Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        });
+        let Some(choice) = response.choices.into_iter().next() else {
+            return Err(ProviderError::MalformedResponse(
+                name.to_string(),
+                "the response contained no choices".to_string(),
+            ));
+        };
+
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(self.content_filtered(choice.message.refusal));
+        }
+
+        match (choice.message.content, choice.message.refusal) {
+            (Some(text), _) => Ok(Completion { text, usage }),
+            (None, Some(refusal)) => Err(self.content_filtered(Some(refusal))),
+            (None, None) => Err(ProviderError::MalformedResponse(
+                name.to_string(),
+                "the response contained no message content".to_string(),
+            )),
+        }
+    }
+
+    /// The error for a completion that was cut off by a moderation filter.
+    fn content_filtered(&self, message: Option<String>) -> ProviderError {
+        ProviderError::ContentFiltered(
+            self.vendor.name().to_string(),
+            message.unwrap_or_else(|| "the response was stopped by a content filter".to_string()),
+        )
+    }
+}
+
+#[async_trait]
+impl AIProvider for ChatCompletionsProvider {
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.completion(&prompt, None).await?.text)
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
+        let response = self.send_request(&prompt, true, None).await?;
+
+        let name = self.vendor.name();
+        let filtered = self.content_filtered(None);
+        let chunks = stream::sse_data(response).filter_map(move |data| {
+            let filtered = filtered.clone();
+            async move {
+                let choice = match data {
+                    Ok(data) if data == "[DONE]" => return None,
+                    Ok(data) => serde_json::from_str::<ChatCompletionsStreamResponse>(&data)
+                        .ok()
+                        .and_then(|response| response.choices.into_iter().next())?,
+                    Err(err) => return Some(Err(ProviderError::from_request(name, err).into())),
+                };
+
+                if choice.finish_reason.as_deref() == Some("content_filter") {
+                    return Some(Err(filtered.into()));
+                }
+
+                choice.delta.content.map(Ok)
+            }
+        });
+
+        Ok(Box::pin(chunks))
+    }
+}
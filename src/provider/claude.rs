@@ -1,98 +1,239 @@
-use super::AIProvider;
-use crate::{command::Git, git_commit::GitCommit};
+use super::error::{self, ProviderError};
+use super::{conversation, stream, AIProvider, Completion, TextStream, Usage};
+use crate::ai_prompt::AIPrompt;
 use async_trait::async_trait;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde_json::json;
 
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+/// The start of the response when JSON is requested.
+const JSON_PREFILL: &str = "{";
+
 pub struct ClaudeProvider {
     client: reqwest::Client,
+    base_url: String,
     api_key: String,
     model: String,
+    max_tokens: Option<u32>,
 }
 
 #[derive(Deserialize)]
 struct ClaudeResponse {
+    #[serde(default)]
     content: Vec<ClaudeContent>,
+    stop_reason: Option<String>,
+    usage: Option<ClaudeUsage>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeUsage {
+    input_tokens: u64,
+    output_tokens: u64,
 }
 
 #[derive(Deserialize)]
 struct ClaudeContent {
-    text: String,
+    text: Option<String>,
+}
+
+/// A single server-sent event of a streamed message. Only
+/// `content_block_delta` events carry text; `error` events replace the rest
+/// of the message when the API fails mid-stream.
+#[derive(Deserialize)]
+struct ClaudeStreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    delta: Option<ClaudeDelta>,
+    error: Option<ClaudeError>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeDelta {
+    text: Option<String>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeError {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+}
+
+impl ClaudeError {
+    /// Maps the error types the API documents onto the statuses it uses for
+    /// them outside of a stream.
+    fn into_provider_error(self) -> ProviderError {
+        let status = match self.error_type.as_str() {
+            "authentication_error" => 401,
+            "permission_error" => 403,
+            "not_found_error" => 404,
+            "request_too_large" => 413,
+            "rate_limit_error" => 429,
+            "overloaded_error" => 529,
+            "api_error" => 500,
+            _ => 400,
+        };
+        let status = reqwest::StatusCode::from_u16(status).unwrap_or_default();
+
+        ProviderError::from_status("Claude", status, None, self.message)
+    }
 }
 
 impl ClaudeProvider {
-    pub fn new(client: reqwest::Client, api_key: String, model: Option<String>) -> Self {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: Option<String>,
+        api_key: String,
+        model: Option<String>,
+        max_tokens: Option<u32>,
+    ) -> Self {
         ClaudeProvider {
             client,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
             api_key,
             model: model.unwrap_or_else(|| "claude-3-5-sonnet-20241022".to_string()),
+            max_tokens,
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// The API has no JSON mode, so when a `schema` is given the response
+    /// is started with `{` on the model's behalf to keep it from adding prose
+    /// around the object.
+    fn create_payload(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> serde_json::Value {
+        let mut messages = conversation(prompt);
+        if schema.is_some() {
+            messages.push(json!({ "role": "assistant", "content": JSON_PREFILL }));
         }
+
+        json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens.unwrap_or(4096),
+            "stream": stream,
+            "system": prompt.system_prompt,
+            "messages": messages,
+        })
+    }
+
+    pub fn request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json")
+            .json(&self.create_payload(prompt, stream, schema))
+    }
+
+    async fn send_request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = self
+            .request(prompt, stream, schema)
+            .send()
+            .await
+            .map_err(|err| ProviderError::from_request("Claude", err))?;
+
+        error::check_response("Claude", response, error::error_message).await
+    }
+
+    pub async fn completion(
+        &self,
+        prompt: &AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Completion, ProviderError> {
+        let response = self.send_request(prompt, false, schema).await?;
+
+        let response: ClaudeResponse = error::parse_json("Claude", response).await?;
+        if response.stop_reason.as_deref() == Some("refusal") {
+            return Err(refused());
+        }
+
+        let text: String = response
+            .content
+            .into_iter()
+            .filter_map(|content| content.text)
+            .collect();
+        if text.is_empty() {
+            return Err(ProviderError::MalformedResponse(
+                "Claude".to_string(),
+                "the response contained no text".to_string(),
+            ));
+        }
+
+        Ok(Completion {
+            text: match schema {
+                Some(_) => format!("{JSON_PREFILL}{text}"),
+                None => text,
+            },
+            usage: response.usage.map(|usage| Usage {
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+            }),
+        })
     }
 }
 
-async fn get_completion_result(
-    client: &reqwest::Client,
-    api_key: &str,
-    payload: serde_json::Value,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await?;
-
-    let claude_response: ClaudeResponse = response.json().await?;
-    Ok(claude_response
-        .content
-        .first()
-        .map(|content| content.text.clone())
-        .unwrap_or_default())
+fn refused() -> ProviderError {
+    ProviderError::ContentFiltered(
+        "Claude".to_string(),
+        "the model refused to respond".to_string(),
+    )
 }
 
 #[async_trait]
 impl AIProvider for ClaudeProvider {
-    async fn explain(&self, git: Git) -> Result<String, Box<dyn std::error::Error>> {
-        let user_input = match git {
-            Git::Commit(ref commit) => {
-                format!(
-                    "Please analyze this git commit and provide a summary.\n\nCommit Message:\n{}\n\nDiff Content:\n{}",
-                    commit.message.as_str(),
-                    commit.diff.as_str()
-                )
-            }
-            Git::Staged(ref staged) => {
-                if !staged.diff.is_empty() {
-                    format!(
-                        "Please analyze the following staged changes and provide a short, concise title and a detailed summary.\n\nDiff Content:\n{}",
-                        staged.diff.as_str()
-                    )
-                } else {
-                    "No commit message or diff content available.".to_string()
-                }
-            }
-        };
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.completion(&prompt, None).await?.text)
+    }
 
-        let payload = json!({
-            "model": self.model,
-            "max_tokens": 4096,
-            "messages": [
-                {
-                    "role": "system",
-                    "content": "You are a helpful assistant that analyzes git commits. \
-                               Provide a concise summary of the changes based on the commit message and diff content. \
-                               Focus on the impact and purpose of the changes."
-                },
-                {
-                    "role": "user",
-                    "content": user_input,
-                }
-            ]
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
+        let response = self.send_request(&prompt, true, None).await?;
+
+        let chunks = stream::sse_data(response).filter_map(|data| async move {
+            let event = match data {
+                Ok(data) => serde_json::from_str::<ClaudeStreamEvent>(&data).ok()?,
+                Err(err) => return Some(Err(ProviderError::from_request("Claude", err).into())),
+            };
+
+            match event.event_type.as_str() {
+                "content_block_delta" => event.delta.and_then(|delta| delta.text).map(Ok),
+                "message_delta" => event
+                    .delta
+                    .and_then(|delta| delta.stop_reason)
+                    .filter(|reason| reason == "refusal")
+                    .map(|_| Err(refused().into())),
+                "error" => event
+                    .error
+                    .map(|error| Err(error.into_provider_error().into())),
+                _ => None,
+            }
         });
 
-        let res = get_completion_result(&self.client, &self.api_key, payload).await?;
-        Ok(res)
+        Ok(Box::pin(chunks))
     }
 }
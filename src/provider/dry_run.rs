@@ -0,0 +1,161 @@
+use reqwest::header::HeaderName;
+
+use super::LumenProvider;
+use crate::{ai_prompt::AIPrompt, config, diff};
+
+/// Headers that carry credentials for the built-in providers. Their values
+/// are never printed, even when they do not contain a configured secret.
+const AUTH_HEADERS: [&str; 4] = ["authorization", "x-api-key", "x-goog-api-key", "api-key"];
+
+/// A request a provider would be sent, shown in place of sending it with
+/// `--dry-run`.
+#[derive(Debug)]
+pub struct DryRun {
+    pub provider: &'static str,
+    pub model: String,
+    pub request: String,
+}
+
+impl std::fmt::Display for DryRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.request)
+    }
+}
+
+impl DryRun {
+    /// Describes `request` as `provider` would be sent it for `prompt`: the
+    /// method and URL, the headers and the JSON body with `secrets`
+    /// redacted, and an estimate of the prompt's size in tokens.
+    pub fn new(
+        provider: &LumenProvider,
+        request: &reqwest::Request,
+        prompt: &AIPrompt,
+        secrets: &[String],
+    ) -> Self {
+        let mut lines = vec![format!(
+            "{} {}",
+            request.method(),
+            redact(request.url().as_str(), secrets)
+        )];
+
+        for (name, value) in request.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            lines.push(format!("{name}: {}", redact_header(name, &value, secrets)));
+        }
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(
+                |body| match serde_json::from_slice::<serde_json::Value>(body) {
+                    Ok(json) => redact(
+                        &serde_json::to_string_pretty(&json).unwrap_or_default(),
+                        secrets,
+                    ),
+                    Err(_) => redact(&String::from_utf8_lossy(body), secrets),
+                },
+            )
+            .unwrap_or_default();
+
+        let tokens = diff::estimate_tokens(&prompt.system_prompt)
+            + prompt
+                .history
+                .iter()
+                .map(|message| diff::estimate_tokens(&message.content))
+                .sum::<usize>()
+            + diff::estimate_tokens(&prompt.user_prompt);
+
+        DryRun {
+            provider: provider.name(),
+            model: provider.model().to_string(),
+            request: format!(
+                "{}\n\n{body}\n\nEstimated prompt tokens: {tokens}",
+                lines.join("\n")
+            ),
+        }
+    }
+}
+
+fn redact_header(name: &HeaderName, value: &str, secrets: &[String]) -> String {
+    let redacted = redact(value, secrets);
+    if redacted == value && AUTH_HEADERS.contains(&name.as_str()) {
+        return config::redact(value);
+    }
+
+    redacted
+}
+
+/// `text` with every occurrence of a secret shortened to its first few
+/// characters.
+fn redact(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), &config::redact(secret))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LumenConfig;
+    use crate::provider::chain::ProviderChain;
+    use crate::ProviderType;
+
+    const KEY: &str = "sk-test-0123456789abcdefghijklmnop";
+
+    fn dry_run(provider: ProviderType) -> DryRun {
+        let mut config = LumenConfig::defaults();
+        config.provider.value = provider;
+        config.api_key.value = Some(KEY.to_string());
+        let Ok(chain) = ProviderChain::new(reqwest::Client::new(), &config) else {
+            panic!("{provider:?} needs more than an API key");
+        };
+
+        let prompt = AIPrompt {
+            system_prompt: "Explain the change.".to_string(),
+            user_prompt: "diff --git a/src/main.rs b/src/main.rs".to_string(),
+            history: Vec::new(),
+        };
+        chain.dry_run(&prompt, true, None).unwrap().remove(0)
+    }
+
+    #[test]
+    fn hides_api_keys_in_every_header() {
+        for (provider, header) in [
+            (ProviderType::Openai, "authorization: Bearer sk-t..."),
+            (ProviderType::Claude, "x-api-key: sk-t..."),
+            (ProviderType::Gemini, "x-goog-api-key: sk-t..."),
+        ] {
+            let request = dry_run(provider).request;
+            assert!(!request.contains(KEY), "{request}");
+            assert!(request.contains(header), "{request}");
+        }
+    }
+
+    #[test]
+    fn hides_credentials_that_are_not_configured_secrets() {
+        let name = HeaderName::from_static("authorization");
+        assert_eq!(
+            redact_header(&name, &format!("Bearer {KEY}"), &[]),
+            "Bear..."
+        );
+
+        let name = HeaderName::from_static("content-type");
+        assert_eq!(
+            redact_header(&name, "application/json", &[]),
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn redacts_secrets_wherever_they_appear() {
+        let secrets = [KEY.to_string(), String::new()];
+        let text = format!("https://example.com/?key={KEY}\n{{\"token\": \"{KEY}\"}}");
+        assert_eq!(
+            redact(&text, &secrets),
+            "https://example.com/?key=sk-t...\n{\"token\": \"sk-t...\"}"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use std::time::SystemTime;
+
+/// Phrases providers use when a request does not fit in the model's context
+/// window. Most report it as a plain 400 Bad Request.
+const CONTEXT_LENGTH_HINTS: [&str; 6] = [
+    "context length",
+    "context_length",
+    "context window",
+    "maximum context",
+    "too many tokens",
+    "prompt is too long",
+];
+
+/// Phrases providers use for invalid keys when they answer 400 rather than
+/// 401, as Gemini does.
+const AUTH_HINTS: [&str; 3] = ["api key not valid", "invalid api key", "incorrect api key"];
+
+/// Error codes OpenAI and Azure OpenAI report in `{"error": {"code": ...}}`
+/// when a moderation filter rejected the prompt.
+const CONTENT_FILTER_CODES: [&str; 2] = ["content_filter", "content_policy_violation"];
+
+/// A request that reached the provider but did not produce a completion.
+/// Every variant names the provider and carries its error message.
+#[derive(Debug, Clone)]
+pub enum ProviderError {
+    AuthFailed(String, String),
+    RateLimited(String, Option<u64>, String),
+    ContextLengthExceeded(String, String),
+    ContentFiltered(String, String),
+    ServerError(String, u16, String),
+    RequestFailed(String, u16, String),
+    MalformedResponse(String, String),
+    NetworkError(String, String),
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::AuthFailed(provider, message) => {
+                write!(f, "{provider} rejected the API key: {message}")
+            }
+            ProviderError::RateLimited(provider, Some(seconds), message) => write!(
+                f,
+                "{provider} rate limit exceeded, retry after {seconds}s: {message}"
+            ),
+            ProviderError::RateLimited(provider, None, message) => {
+                write!(f, "{provider} rate limit exceeded: {message}")
+            }
+            ProviderError::ContextLengthExceeded(provider, message) => write!(
+                f,
+                "The prompt is too long for the {provider} model: {message} \
+                 (hint: set a smaller context window for the model in [context_windows] \
+                 so that large diffs are summarized in parts)"
+            ),
+            ProviderError::ContentFiltered(provider, message) => {
+                write!(f, "{provider} blocked the content: {message}")
+            }
+            ProviderError::ServerError(provider, status, message) => {
+                write!(f, "{provider} server error ({status}): {message}")
+            }
+            ProviderError::RequestFailed(provider, status, message) => {
+                write!(f, "{provider} returned {status}: {message}")
+            }
+            ProviderError::MalformedResponse(provider, message) => {
+                write!(f, "{provider} returned an unexpected response: {message}")
+            }
+            ProviderError::NetworkError(provider, message) => {
+                write!(f, "Request to {provider} failed: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+impl ProviderError {
+    /// Classifies an error response by its status code and, for the 400s
+    /// that providers use for several kinds of problem, its message.
+    pub fn from_status(
+        provider: &str,
+        status: StatusCode,
+        retry_after: Option<u64>,
+        message: String,
+    ) -> Self {
+        let provider = provider.to_string();
+        let lowercase = message.to_lowercase();
+        let mentions = |hints: &[&str]| hints.iter().any(|hint| lowercase.contains(hint));
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                ProviderError::AuthFailed(provider, message)
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                ProviderError::RateLimited(provider, retry_after, message)
+            }
+            StatusCode::PAYLOAD_TOO_LARGE => {
+                ProviderError::ContextLengthExceeded(provider, message)
+            }
+            _ if status.is_server_error() || status.as_u16() == 529 => {
+                ProviderError::ServerError(provider, status.as_u16(), message)
+            }
+            _ if mentions(&AUTH_HINTS) => ProviderError::AuthFailed(provider, message),
+            _ if mentions(&CONTEXT_LENGTH_HINTS) => {
+                ProviderError::ContextLengthExceeded(provider, message)
+            }
+            _ => ProviderError::RequestFailed(provider, status.as_u16(), message),
+        }
+    }
+
+    /// Whether the same request may succeed when sent again later.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ProviderError::RateLimited(..)
+                | ProviderError::ServerError(..)
+                | ProviderError::NetworkError(..)
+        )
+    }
+
+    /// Whether another provider may succeed where this one failed: it is
+    /// down, overloaded or answering with something that is not a completion.
+    pub fn is_unavailable(&self) -> bool {
+        self.is_transient() || matches!(self, ProviderError::MalformedResponse(..))
+    }
+
+    /// Wraps errors sending a request or reading its response.
+    pub fn from_request(provider: &str, err: reqwest::Error) -> Self {
+        ProviderError::NetworkError(provider.to_string(), err.to_string())
+    }
+}
+
+/// Passes successful responses through, and turns error responses into a
+/// [`ProviderError`] using the message `error_message` finds in the body.
+/// Responses with a content filter error code are reported as filtered
+/// whatever their status.
+pub async fn check_response(
+    provider: &str,
+    response: reqwest::Response,
+    error_message: impl Fn(&str) -> Option<String>,
+) -> Result<reqwest::Response, ProviderError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| retry_after_secs(value, SystemTime::now()));
+    let body = response
+        .text()
+        .await
+        .map_err(|err| ProviderError::from_request(provider, err))?;
+    let message = error_message(&body).unwrap_or_else(|| match body.trim() {
+        "" => status.canonical_reason().unwrap_or_default().to_string(),
+        body => body.to_string(),
+    });
+
+    if error_code(&body).is_some_and(|code| CONTENT_FILTER_CODES.contains(&code.as_str())) {
+        return Err(ProviderError::ContentFiltered(
+            provider.to_string(),
+            message,
+        ));
+    }
+
+    Err(ProviderError::from_status(
+        provider,
+        status,
+        retry_after,
+        message,
+    ))
+}
+
+/// The seconds to wait that a `Retry-After` header asks for, given either as
+/// a number of seconds or as an HTTP date. Dates in the past mean no wait.
+fn retry_after_secs(value: &str, now: SystemTime) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    let wait = date.duration_since(now).unwrap_or_default();
+    // A wait that ends partway through a second is rounded up
+    Some(wait.as_secs() + u64::from(wait.subsec_nanos() > 0))
+}
+
+/// Finds the machine-readable code in `{"error": {"code": "..."}}`.
+fn error_code(body: &str) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_str(body).ok()?;
+    body["error"]["code"].as_str().map(String::from)
+}
+
+/// Finds the message in the error bodies most providers use:
+/// `{"error": {"message": "..."}}`, `{"error": "..."}` or
+/// `{"message": "..."}`.
+pub fn error_message(body: &str) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_str(body).ok()?;
+
+    let message = [&body["error"]["message"], &body["error"], &body["message"]]
+        .into_iter()
+        .find_map(|value| value.as_str())
+        .map(String::from);
+
+    message
+}
+
+/// Parses a JSON response body, reporting bodies that do not have the
+/// expected shape as malformed.
+pub async fn parse_json<T: serde::de::DeserializeOwned>(
+    provider: &str,
+    response: reqwest::Response,
+) -> Result<T, ProviderError> {
+    let body = response
+        .text()
+        .await
+        .map_err(|err| ProviderError::from_request(provider, err))?;
+
+    serde_json::from_str(&body).map_err(|err| {
+        ProviderError::MalformedResponse(
+            provider.to_string(),
+            format!("{err} in {}", excerpt(&body)),
+        )
+    })
+}
+
+/// The start of a response body, for error messages.
+fn excerpt(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(200) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_error_codes() {
+        let body = r#"{"error": {"message": "filtered", "code": "content_filter"}}"#;
+        assert_eq!(error_code(body).as_deref(), Some("content_filter"));
+        assert_eq!(error_code(r#"{"error": "filtered"}"#), None);
+        assert_eq!(error_code("not json"), None);
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let now = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(retry_after_secs("120", now), Some(120));
+        assert_eq!(retry_after_secs(" 7 ", now), Some(7));
+        assert_eq!(
+            retry_after_secs("Sun, 06 Nov 1994 08:50:07 GMT", now),
+            Some(30)
+        );
+        assert_eq!(
+            retry_after_secs("Sunday, 06-Nov-94 08:49:57 GMT", now),
+            Some(20)
+        );
+        assert_eq!(
+            retry_after_secs("Sun, 06 Nov 1994 08:00:00 GMT", now),
+            Some(0)
+        );
+        assert_eq!(retry_after_secs("soon", now), None);
+    }
+
+    #[test]
+    fn mentioning_safety_is_not_a_content_filter() {
+        let error = ProviderError::from_status(
+            "OpenAI",
+            StatusCode::BAD_REQUEST,
+            None,
+            "Invalid value for 'safety_identifier'".to_string(),
+        );
+        assert!(matches!(error, ProviderError::RequestFailed(..)));
+    }
+}
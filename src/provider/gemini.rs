@@ -0,0 +1,337 @@
+use crate::ai_prompt::{AIPrompt, Role};
+
+use super::error::{self, ProviderError};
+use super::{stream, AIProvider, Completion, TextStream, Usage};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+pub const DEFAULT_MODEL: &str = "gemini-1.5-flash";
+
+/// Finish reasons that mean the response was withheld rather than completed.
+const BLOCKED_FINISH_REASONS: [&str; 5] = [
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+struct Part {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Deserialize)]
+struct SafetyRating {
+    category: String,
+    #[serde(default)]
+    blocked: bool,
+    probability: Option<String>,
+}
+
+impl GeminiResponse {
+    /// The text of the first candidate, or the reason it was blocked.
+    fn into_text(self) -> Result<String, ProviderError> {
+        if let Some(feedback) = self.prompt_feedback {
+            if let Some(reason) = feedback.block_reason {
+                return Err(blocked(
+                    "prompt",
+                    &reason,
+                    &flagged_categories(&feedback.safety_ratings),
+                ));
+            }
+        }
+
+        let Some(candidate) = self.candidates.into_iter().next() else {
+            return Ok(String::new());
+        };
+
+        if let Some(reason) = candidate
+            .finish_reason
+            .filter(|reason| BLOCKED_FINISH_REASONS.contains(&reason.as_str()))
+        {
+            return Err(blocked(
+                "response",
+                &reason,
+                &flagged_categories(&candidate.safety_ratings),
+            ));
+        }
+
+        Ok(candidate
+            .content
+            .map(|content| {
+                content
+                    .parts
+                    .into_iter()
+                    .filter_map(|part| part.text)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+impl GeminiProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: Option<String>,
+        api_key: String,
+        model: Option<String>,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        GeminiProvider {
+            client,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            max_tokens,
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn create_payload(
+        &self,
+        prompt: &AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> serde_json::Value {
+        // Gemini calls the assistant `model`
+        let contents: Vec<serde_json::Value> = prompt
+            .history
+            .iter()
+            .map(|message| match message.role {
+                Role::User => ("user", &message.content),
+                Role::Assistant => ("model", &message.content),
+            })
+            .chain([("user", &prompt.user_prompt)])
+            .map(|(role, text)| json!({ "role": role, "parts": [{ "text": text }] }))
+            .collect();
+
+        let mut payload = json!({
+            "systemInstruction": {
+                "parts": [{ "text": prompt.system_prompt }]
+            },
+            "contents": contents,
+        });
+
+        let mut generation_config = serde_json::Map::new();
+        if let Some(max_tokens) = self.max_tokens {
+            generation_config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+        }
+        if let Some(schema) = schema {
+            generation_config.insert("responseMimeType".to_string(), json!("application/json"));
+            generation_config.insert("responseSchema".to_string(), response_schema(schema));
+        }
+        if !generation_config.is_empty() {
+            payload["generationConfig"] = generation_config.into();
+        }
+
+        payload
+    }
+
+    /// A call to `generateContent`, or `streamGenerateContent` with
+    /// server-sent events when streaming.
+    pub fn request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> reqwest::RequestBuilder {
+        let url = if stream {
+            format!(
+                "{}/models/{}:streamGenerateContent?alt=sse",
+                self.base_url, self.model
+            )
+        } else {
+            format!("{}/models/{}:generateContent", self.base_url, self.model)
+        };
+
+        self.client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&self.create_payload(prompt, schema))
+    }
+
+    async fn send_request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = self
+            .request(prompt, stream, schema)
+            .send()
+            .await
+            .map_err(|err| ProviderError::from_request("Gemini", err))?;
+
+        error::check_response("Gemini", response, error::error_message).await
+    }
+
+    pub async fn completion(
+        &self,
+        prompt: &AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Completion, ProviderError> {
+        let response = self.send_request(prompt, false, schema).await?;
+        let mut response: GeminiResponse = error::parse_json("Gemini", response).await?;
+
+        let usage = response.usage_metadata.take().map(|usage| Usage {
+            input_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count,
+        });
+        let text = response.into_text()?;
+        if text.is_empty() {
+            return Err(ProviderError::MalformedResponse(
+                "Gemini".to_string(),
+                "the response contained no text".to_string(),
+            ));
+        }
+
+        Ok(Completion { text, usage })
+    }
+}
+
+/// `schema` in the subset of OpenAPI that Gemini accepts, which does not
+/// include `additionalProperties`.
+fn response_schema(schema: &serde_json::Value) -> serde_json::Value {
+    match schema {
+        serde_json::Value::Object(object) => object
+            .iter()
+            .filter(|(key, _)| key.as_str() != "additionalProperties")
+            .map(|(key, value)| (key.clone(), response_schema(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        serde_json::Value::Array(values) => values.iter().map(response_schema).collect(),
+        value => value.clone(),
+    }
+}
+
+/// The categories a safety filter blocked, or else those rated as anything
+/// but negligible.
+fn flagged_categories(ratings: &[SafetyRating]) -> Vec<String> {
+    let blocked: Vec<String> = ratings
+        .iter()
+        .filter(|rating| rating.blocked)
+        .map(|rating| rating.category.clone())
+        .collect();
+    if !blocked.is_empty() {
+        return blocked;
+    }
+
+    ratings
+        .iter()
+        .filter(|rating| {
+            rating
+                .probability
+                .as_deref()
+                .is_some_and(|probability| probability != "NEGLIGIBLE")
+        })
+        .map(|rating| rating.category.clone())
+        .collect()
+}
+
+/// The error for a prompt or response withheld by a safety filter.
+fn blocked(what: &str, reason: &str, categories: &[String]) -> ProviderError {
+    let message = if categories.is_empty() {
+        format!("the {what} was flagged for {reason}")
+    } else {
+        format!(
+            "the {what} was flagged for {reason} ({})",
+            categories.join(", ")
+        )
+    };
+
+    ProviderError::ContentFiltered("Gemini".to_string(), message)
+}
+
+#[async_trait]
+impl AIProvider for GeminiProvider {
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.completion(&prompt, None).await?.text)
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
+        let response = self.send_request(&prompt, true, None).await?;
+
+        let chunks = stream::sse_data(response).filter_map(|data| async move {
+            let data = match data {
+                Ok(data) => data,
+                Err(err) => return Some(Err(ProviderError::from_request("Gemini", err).into())),
+            };
+
+            match serde_json::from_str::<GeminiResponse>(&data) {
+                Ok(response) => match response.into_text() {
+                    Ok(text) if text.is_empty() => None,
+                    Ok(text) => Some(Ok(text)),
+                    Err(err) => Some(Err(err.into())),
+                },
+                Err(err) => Some(Err(ProviderError::MalformedResponse(
+                    "Gemini".to_string(),
+                    err.to_string(),
+                )
+                .into())),
+            }
+        });
+
+        Ok(Box::pin(chunks))
+    }
+}
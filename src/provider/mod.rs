@@ -1,69 +1,394 @@
+use std::pin::Pin;
+
 use async_trait::async_trait;
+use chat_completions::{ChatCompletionsProvider, Vendor};
 use claude::ClaudeProvider;
-use groq::GroqProvider;
-use openai::OpenAIProvider;
+use futures::Stream;
+use gemini::GeminiProvider;
+use ollama::OllamaProvider;
 use phind::PhindProvider;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
 
-use crate::{command::Git, error::LumenError, git_commit::GitCommit, ProviderType};
+use crate::{ai_prompt::AIPrompt, config::LumenConfig, error::LumenError, ProviderType};
+use error::ProviderError;
 
+pub mod chain;
+pub mod chat_completions;
 pub mod claude;
-pub mod groq;
-pub mod openai;
+pub mod dry_run;
+pub mod error;
+pub mod gemini;
+pub mod ollama;
 pub mod phind;
+pub mod stream;
+
+/// Context windows of commonly used models in tokens, matched by prefix in
+/// order. Models not listed here can be configured in `[context_windows]`.
+const CONTEXT_WINDOWS: [(&str, u32); 17] = [
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 128_000),
+    ("claude-", 200_000),
+    ("mistral-large", 128_000),
+    ("mistral-small", 32_000),
+    ("codestral", 256_000),
+    ("deepseek-", 64_000),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-", 1_048_576),
+    ("mixtral-8x7b-32768", 32_768),
+    ("llama-3.1", 131_072),
+    ("llama3-70b-8192", 8_192),
+    ("llama3-8b-8192", 8_192),
+    ("Phind-70B", 32_000),
+];
+
+const DEFAULT_CONTEXT_WINDOW: u32 = 8_192;
+
+/// Text chunks of a completion, yielded as the provider produces them.
+pub type TextStream =
+    Pin<Box<dyn Stream<Item = Result<String, Box<dyn std::error::Error + Send + Sync>>> + Send>>;
+
+/// The tokens a completion took, as reported by the provider.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// A full response, along with its token usage for providers that report
+/// it.
+pub struct Completion {
+    pub text: String,
+    pub usage: Option<Usage>,
+}
 
 #[async_trait]
 pub trait AIProvider {
-    async fn explain(&self, commit: Git) -> Result<String, Box<dyn std::error::Error>>;
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>>;
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>>;
 }
 
 pub enum LumenProvider {
-    OpenAI(Box<OpenAIProvider>),
+    OpenAI(Box<ChatCompletionsProvider>),
     Phind(Box<PhindProvider>),
-    Groq(Box<GroqProvider>),
+    Groq(Box<ChatCompletionsProvider>),
     Claude(Box<ClaudeProvider>),
+    Ollama(Box<OllamaProvider>),
+    OpenAICompatible(Box<ChatCompletionsProvider>),
+    Mistral(Box<ChatCompletionsProvider>),
+    DeepSeek(Box<ChatCompletionsProvider>),
+    Gemini(Box<GeminiProvider>),
 }
 
 impl LumenProvider {
-    pub fn new(
-        client: reqwest::Client,
-        provider_type: ProviderType,
-        api_key: Option<String>,
-        model: Option<String>,
-    ) -> Result<Self, LumenError> {
-        match provider_type {
-            ProviderType::Openai => {
-                let api_key = api_key.ok_or(LumenError::MissingApiKey("OpenAI".to_string()))?;
-                let provider =
-                    LumenProvider::OpenAI(Box::new(OpenAIProvider::new(client, api_key, model)));
-                Ok(provider)
-            }
+    pub fn new(client: reqwest::Client, config: &LumenConfig) -> Result<Self, LumenError> {
+        let api_key = config.api_key.value.clone();
+        let model = config.model.value.clone();
+        let max_tokens = config.max_tokens.value;
+
+        match config.provider.value {
+            ProviderType::Openai => Ok(LumenProvider::OpenAI(Box::new(chat_completions(
+                client,
+                Vendor::OpenAI,
+                config,
+            )?))),
             ProviderType::Phind => Ok(LumenProvider::Phind(Box::new(PhindProvider::new(
                 client, None,
             )))),
-            ProviderType::Groq => {
-                let api_key = api_key.ok_or(LumenError::MissingApiKey("Groq".to_string()))?;
-                let provider =
-                    LumenProvider::Groq(Box::new(GroqProvider::new(client, api_key, model)));
-                Ok(provider)
-            }
+            ProviderType::Groq => Ok(LumenProvider::Groq(Box::new(chat_completions(
+                client,
+                Vendor::Groq,
+                config,
+            )?))),
+            ProviderType::Mistral => Ok(LumenProvider::Mistral(Box::new(chat_completions(
+                client,
+                Vendor::Mistral,
+                config,
+            )?))),
+            ProviderType::Deepseek => Ok(LumenProvider::DeepSeek(Box::new(chat_completions(
+                client,
+                Vendor::DeepSeek,
+                config,
+            )?))),
+            ProviderType::OpenaiCompatible => Ok(LumenProvider::OpenAICompatible(Box::new(
+                chat_completions(client, Vendor::Compatible, config)?,
+            ))),
             ProviderType::Claude => {
                 let api_key = api_key.ok_or(LumenError::MissingApiKey("Claude".to_string()))?;
-                let provider =
-                    LumenProvider::Claude(Box::new(ClaudeProvider::new(client, api_key, model)));
+                let provider = LumenProvider::Claude(Box::new(ClaudeProvider::new(
+                    client,
+                    config.base_url.value.clone(),
+                    api_key,
+                    model,
+                    max_tokens,
+                )));
+                Ok(provider)
+            }
+            ProviderType::Gemini => {
+                let api_key = api_key.ok_or(LumenError::MissingApiKey("Gemini".to_string()))?;
+                let provider = LumenProvider::Gemini(Box::new(GeminiProvider::new(
+                    client,
+                    config.base_url.value.clone(),
+                    api_key,
+                    model,
+                    max_tokens,
+                )));
+                Ok(provider)
+            }
+            ProviderType::Ollama => {
+                let model_name = model.as_deref().unwrap_or(ollama::DEFAULT_MODEL);
+                let context_window = context_window(model_name, config);
+                let provider = LumenProvider::Ollama(Box::new(OllamaProvider::new(
+                    client,
+                    config.base_url.value.clone(),
+                    model,
+                    max_tokens,
+                    context_window,
+                )));
                 Ok(provider)
             }
         }
     }
+
+    /// The name of the provider on the command line and in the config.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LumenProvider::OpenAI(_) => "openai",
+            LumenProvider::Phind(_) => "phind",
+            LumenProvider::Groq(_) => "groq",
+            LumenProvider::Claude(_) => "claude",
+            LumenProvider::Ollama(_) => "ollama",
+            LumenProvider::OpenAICompatible(_) => "openai-compatible",
+            LumenProvider::Mistral(_) => "mistral",
+            LumenProvider::DeepSeek(_) => "deepseek",
+            LumenProvider::Gemini(_) => "gemini",
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        match self {
+            LumenProvider::OpenAI(provider) => provider.model(),
+            LumenProvider::Phind(provider) => provider.model(),
+            LumenProvider::Groq(provider) => provider.model(),
+            LumenProvider::Claude(provider) => provider.model(),
+            LumenProvider::Ollama(provider) => provider.model(),
+            LumenProvider::OpenAICompatible(provider) => provider.model(),
+            LumenProvider::Mistral(provider) => provider.model(),
+            LumenProvider::DeepSeek(provider) => provider.model(),
+            LumenProvider::Gemini(provider) => provider.model(),
+        }
+    }
+
+    /// The request that completing `prompt` sends, ready to be sent or
+    /// inspected. With a JSON `schema`, structured output is requested from
+    /// providers that support it.
+    pub fn request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> reqwest::RequestBuilder {
+        match self {
+            LumenProvider::OpenAI(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::Phind(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::Groq(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::Claude(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::Ollama(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::OpenAICompatible(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::Mistral(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::DeepSeek(provider) => provider.request(prompt, stream, schema),
+            LumenProvider::Gemini(provider) => provider.request(prompt, stream, schema),
+        }
+    }
+
+    /// Completes `prompt` without streaming, asking for output following
+    /// `schema` where the provider supports structured output.
+    pub async fn completion(
+        &self,
+        prompt: &AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Completion, ProviderError> {
+        match self {
+            LumenProvider::OpenAI(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::Phind(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::Groq(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::Claude(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::Ollama(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::OpenAICompatible(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::Mistral(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::DeepSeek(provider) => provider.completion(prompt, schema).await,
+            LumenProvider::Gemini(provider) => provider.completion(prompt, schema).await,
+        }
+    }
+
+    /// How many tokens the model accepts, prompt and response combined.
+    pub fn context_window(&self, config: &LumenConfig) -> u32 {
+        context_window(self.model(), config)
+    }
+
+    /// The models the provider offers, for providers that can list them.
+    pub async fn list_models(&self) -> Result<Vec<String>, LumenError> {
+        match self {
+            LumenProvider::Ollama(provider) => Ok(provider.list_models().await?),
+            LumenProvider::OpenAI(_) => {
+                Err(LumenError::ModelListingNotSupported("OpenAI".to_string()))
+            }
+            LumenProvider::Phind(_) => {
+                Err(LumenError::ModelListingNotSupported("Phind".to_string()))
+            }
+            LumenProvider::Groq(_) => Err(LumenError::ModelListingNotSupported("Groq".to_string())),
+            LumenProvider::Claude(_) => {
+                Err(LumenError::ModelListingNotSupported("Claude".to_string()))
+            }
+            LumenProvider::OpenAICompatible(_) => Err(LumenError::ModelListingNotSupported(
+                "openai-compatible".to_string(),
+            )),
+            LumenProvider::Gemini(_) => {
+                Err(LumenError::ModelListingNotSupported("Gemini".to_string()))
+            }
+            LumenProvider::Mistral(_) => {
+                Err(LumenError::ModelListingNotSupported("Mistral".to_string()))
+            }
+            LumenProvider::DeepSeek(_) => {
+                Err(LumenError::ModelListingNotSupported("DeepSeek".to_string()))
+            }
+        }
+    }
+}
+
+/// The earlier turns and the user prompt of `prompt` as `role` and
+/// `content` messages, which is how most chat APIs take a conversation.
+fn conversation(prompt: &AIPrompt) -> Vec<serde_json::Value> {
+    prompt
+        .history
+        .iter()
+        .map(|message| (message.role.name(), &message.content))
+        .chain([("user", &prompt.user_prompt)])
+        .map(|(role, content)| serde_json::json!({ "role": role, "content": content }))
+        .collect()
+}
+
+/// A chat completions client for `vendor`, using the configured base URL and
+/// model in place of the vendor's defaults.
+fn chat_completions(
+    client: reqwest::Client,
+    vendor: Vendor,
+    config: &LumenConfig,
+) -> Result<ChatCompletionsProvider, LumenError> {
+    let missing =
+        |setting: &str| LumenError::MissingSetting(setting.to_string(), vendor.name().to_string());
+
+    let api_key = config.api_key.value.as_deref();
+    if api_key.is_none() && vendor.requires_api_key() {
+        return Err(LumenError::MissingApiKey(vendor.name().to_string()));
+    }
+
+    let base_url = config
+        .base_url
+        .value
+        .as_deref()
+        .or(vendor.base_url())
+        .ok_or_else(|| missing("base_url"))?;
+    let model = config
+        .model
+        .value
+        .clone()
+        .or_else(|| vendor.default_model().map(String::from))
+        .ok_or_else(|| missing("model"))?;
+
+    Ok(ChatCompletionsProvider::new(
+        client,
+        vendor,
+        base_url,
+        chat_completions_headers(api_key, config)?,
+        model,
+        config.max_tokens.value,
+    ))
+}
+
+/// The headers sent with every chat completions request: the API key in the
+/// configured `auth_header` (with `auth_scheme` in front of it, if not empty)
+/// and the configured extra `headers`.
+fn chat_completions_headers(
+    api_key: Option<&str>,
+    config: &LumenConfig,
+) -> Result<HeaderMap, LumenError> {
+    let mut headers = HeaderMap::new();
+    let mut insert = |name: &str, value: &str| {
+        let invalid = |err: &dyn std::error::Error| {
+            LumenError::InvalidHeader(name.to_string(), err.to_string())
+        };
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| invalid(&err))?;
+        let header_value = HeaderValue::from_str(value).map_err(|err| invalid(&err))?;
+        headers.insert(header_name, header_value);
+        Ok::<(), LumenError>(())
+    };
+
+    if let Some(api_key) = api_key {
+        let scheme = config.auth_scheme.value.trim();
+        let value = if scheme.is_empty() {
+            api_key.to_string()
+        } else {
+            format!("{scheme} {api_key}")
+        };
+        insert(&config.auth_header.value, &value)?;
+    }
+
+    for (name, value) in &config.headers {
+        insert(name, &value.value)?;
+    }
+
+    Ok(headers)
+}
+
+/// The context window of `model`, as configured in `[context_windows]` or
+/// known from [`CONTEXT_WINDOWS`].
+fn context_window(model: &str, config: &LumenConfig) -> u32 {
+    config.context_window(model).unwrap_or_else(|| {
+        CONTEXT_WINDOWS
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix))
+            .map(|(_, tokens)| *tokens)
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    })
 }
 
 #[async_trait]
 impl AIProvider for LumenProvider {
-    async fn explain(&self, commit: Git) -> Result<String, Box<dyn std::error::Error>> {
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            LumenProvider::OpenAI(provider) => provider.complete(prompt).await,
+            LumenProvider::Phind(provider) => provider.complete(prompt).await,
+            LumenProvider::Groq(provider) => provider.complete(prompt).await,
+            LumenProvider::Claude(provider) => provider.complete(prompt).await,
+            LumenProvider::Ollama(provider) => provider.complete(prompt).await,
+            LumenProvider::OpenAICompatible(provider) => provider.complete(prompt).await,
+            LumenProvider::Mistral(provider) => provider.complete(prompt).await,
+            LumenProvider::DeepSeek(provider) => provider.complete(prompt).await,
+            LumenProvider::Gemini(provider) => provider.complete(prompt).await,
+        }
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
         match self {
-            LumenProvider::OpenAI(provider) => provider.explain(commit).await,
-            LumenProvider::Phind(provider) => provider.explain(commit).await,
-            LumenProvider::Groq(provider) => provider.explain(commit).await,
-            LumenProvider::Claude(provider) => provider.explain(commit).await,
+            LumenProvider::OpenAI(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::Phind(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::Groq(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::Claude(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::Ollama(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::OpenAICompatible(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::Mistral(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::DeepSeek(provider) => provider.complete_stream(prompt).await,
+            LumenProvider::Gemini(provider) => provider.complete_stream(prompt).await,
         }
     }
 }
@@ -0,0 +1,404 @@
+use crate::ai_prompt::AIPrompt;
+
+use super::error::{self, ProviderError};
+use super::{conversation, stream, AIProvider, Completion, TextStream, Usage};
+use async_trait::async_trait;
+use futures::{future, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+pub const DEFAULT_MODEL: &str = "llama3.1";
+
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    max_tokens: Option<u32>,
+    context_window: u32,
+}
+
+/// A response from `/api/chat`. When streaming, every line of the body is
+/// one of these, and the last one has `done` set.
+#[derive(Deserialize)]
+struct OllamaResponse {
+    message: Option<Message>,
+    error: Option<String>,
+    #[serde(default)]
+    done: bool,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<Model>,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    name: String,
+}
+
+impl OllamaProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: Option<String>,
+        model: Option<String>,
+        max_tokens: Option<u32>,
+        context_window: u32,
+    ) -> Self {
+        OllamaProvider {
+            client,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            max_tokens,
+            context_window,
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn create_payload(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> serde_json::Value {
+        // Ollama truncates prompts to a small default context unless told
+        // otherwise, which would silently drop most of a large diff
+        let mut options = json!({ "num_ctx": self.context_window });
+        if let Some(max_tokens) = self.max_tokens {
+            options["num_predict"] = json!(max_tokens);
+        }
+
+        let mut messages = vec![json!({
+            "role": "system",
+            "content": prompt.system_prompt,
+        })];
+        messages.extend(conversation(prompt));
+
+        let mut payload = json!({
+            "model": self.model,
+            "stream": stream,
+            "options": options,
+            "messages": messages,
+        });
+
+        if let Some(schema) = schema {
+            payload["format"] = schema.clone();
+        }
+
+        payload
+    }
+
+    /// The models installed in the local Ollama instance.
+    pub async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await
+            .map_err(|err| self.connection_error(err))?;
+
+        let response = error::check_response("Ollama", response, error::error_message).await?;
+        let tags: TagsResponse = error::parse_json("Ollama", response).await?;
+
+        Ok(tags.models.into_iter().map(|model| model.name).collect())
+    }
+
+    pub fn request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&self.create_payload(prompt, stream, schema))
+    }
+
+    async fn send_request(
+        &self,
+        prompt: &AIPrompt,
+        stream: bool,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = self
+            .request(prompt, stream, schema)
+            .send()
+            .await
+            .map_err(|err| self.connection_error(err))?;
+
+        error::check_response("Ollama", response, error::error_message).await
+    }
+
+    pub async fn completion(
+        &self,
+        prompt: &AIPrompt,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<Completion, ProviderError> {
+        let response = self.send_request(prompt, false, schema).await?;
+
+        let response: OllamaResponse = error::parse_json("Ollama", response).await?;
+        if let Some(error) = response.error {
+            return Err(ollama_error(error));
+        }
+
+        let usage = match (response.prompt_eval_count, response.eval_count) {
+            (Some(input_tokens), Some(output_tokens)) => Some(Usage {
+                input_tokens,
+                output_tokens,
+            }),
+            _ => None,
+        };
+        match response.message {
+            Some(message) => Ok(Completion {
+                text: message.content,
+                usage,
+            }),
+            None => Err(ProviderError::MalformedResponse(
+                "Ollama".to_string(),
+                "the response contained no message".to_string(),
+            )),
+        }
+    }
+
+    fn connection_error(&self, err: reqwest::Error) -> ProviderError {
+        if err.is_connect() {
+            return ProviderError::NetworkError(
+                "Ollama".to_string(),
+                format!(
+                    "could not connect to {} (is `ollama serve` running?)",
+                    self.base_url
+                ),
+            );
+        }
+
+        ProviderError::from_request("Ollama", err)
+    }
+}
+
+/// The error Ollama reports in place of a response, e.g. when the model
+/// fails to load.
+fn ollama_error(message: String) -> ProviderError {
+    ProviderError::ServerError("Ollama".to_string(), 500, message)
+}
+
+#[async_trait]
+impl AIProvider for OllamaProvider {
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.completion(&prompt, None).await?.text)
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
+        let response = self.send_request(&prompt, true, None).await?;
+
+        // The body is newline-delimited JSON, one object per chunk
+        let responses = stream::lines(response).filter_map(|line| async move {
+            match line {
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(
+                    serde_json::from_str::<OllamaResponse>(&line).map_err(|err| {
+                        ProviderError::MalformedResponse("Ollama".to_string(), err.to_string())
+                    }),
+                ),
+                Err(err) => Some(Err(ProviderError::from_request("Ollama", err))),
+            }
+        });
+
+        // The completion ends with the response that has `done` set, even if
+        // the connection stays open
+        let responses = responses.scan(false, |done, response| {
+            let response = (!*done).then_some(response);
+            if let Some(Ok(response)) = &response {
+                *done = response.done;
+            }
+            future::ready(response)
+        });
+
+        let chunks = responses.filter_map(|response| async move {
+            match response {
+                Ok(OllamaResponse {
+                    error: Some(error), ..
+                }) => Some(Err(ollama_error(error).into())),
+                Ok(response) => response
+                    .message
+                    .map(|message| message.content)
+                    .filter(|content| !content.is_empty())
+                    .map(Ok),
+                Err(err) => Some(Err(err.into())),
+            }
+        });
+
+        Ok(Box::pin(chunks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    /// A canned response: the chunks of its body, and whether the body ends
+    /// after them or the connection is left open.
+    #[derive(Clone)]
+    struct Route {
+        path: &'static str,
+        chunks: Vec<&'static str>,
+        ends: bool,
+    }
+
+    /// Starts a stand-in for a local Ollama serving `routes`, returning its
+    /// base URL. Every request is answered with the chunks of the route for
+    /// its path, sent one at a time.
+    async fn serve(routes: Vec<Route>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let routes = routes.clone();
+                tokio::spawn(async move {
+                    let path = read_request(&mut socket).await;
+                    let route = routes.iter().find(|route| route.path == path).unwrap();
+
+                    socket
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\n\
+                              Transfer-Encoding: chunked\r\n\r\n",
+                        )
+                        .await
+                        .unwrap();
+                    for chunk in &route.chunks {
+                        let chunk = format!("{:x}\r\n{chunk}\r\n", chunk.len());
+                        socket.write_all(chunk.as_bytes()).await.unwrap();
+                        socket.flush().await.unwrap();
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+
+                    match route.ends {
+                        true => socket.write_all(b"0\r\n\r\n").await.unwrap(),
+                        false => tokio::time::sleep(Duration::from_secs(60)).await,
+                    }
+                });
+            }
+        });
+
+        format!("http://{address}")
+    }
+
+    /// Reads a request up to the end of its body, returning its path.
+    async fn read_request(socket: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&request);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|n| n.trim().parse().unwrap())
+                })
+                .unwrap_or(0);
+            if body.len() >= length {
+                return head.split(' ').nth(1).unwrap().to_string();
+            }
+        }
+    }
+
+    fn provider(base_url: String) -> OllamaProvider {
+        OllamaProvider::new(reqwest::Client::new(), Some(base_url), None, None, 8_192)
+    }
+
+    #[tokio::test]
+    async fn streams_chunks_until_done() {
+        let base_url = serve(vec![Route {
+            path: "/api/chat",
+            chunks: vec![
+                "{\"message\":{\"content\":\"Adds \"},\"done\":false}\n",
+                // A line split across chunks
+                "{\"message\":{\"content\":\"a ret",
+                "ry\"},\"done\":false}\n{\"message\":{\"content\":\" loop.\"},\"done\":false}\n",
+                "{\"message\":{\"content\":\"\"},\"done\":true,\"eval_count\":3}\n",
+                "{\"message\":{\"content\":\"after done\"},\"done\":false}\n",
+            ],
+            ends: false,
+        }])
+        .await;
+
+        let prompt = AIPrompt {
+            system_prompt: "You summarize diffs.".to_string(),
+            user_prompt: "Summarize".to_string(),
+            history: Vec::new(),
+        };
+        let stream = provider(base_url).complete_stream(prompt).await.unwrap();
+        let chunks: Vec<String> =
+            tokio::time::timeout(Duration::from_secs(5), stream.collect::<Vec<_>>())
+                .await
+                .expect("the stream ends at done")
+                .into_iter()
+                .map(Result::unwrap)
+                .collect();
+
+        assert_eq!(chunks, ["Adds ", "a retry", " loop."]);
+    }
+
+    #[tokio::test]
+    async fn lists_installed_models() {
+        let base_url = serve(vec![Route {
+            path: "/api/tags",
+            chunks: vec![
+                "{\"models\":[{\"name\":\"llama3.1:8b\",\"size\":1},",
+                "{\"name\":\"qwen2.5-coder:7b\",\"size\":2}]}",
+            ],
+            ends: true,
+        }])
+        .await;
+
+        let models = provider(base_url).list_models().await.unwrap();
+
+        assert_eq!(models, ["llama3.1:8b", "qwen2.5-coder:7b"]);
+    }
+
+    #[tokio::test]
+    async fn reports_a_missing_server() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let err = provider(base_url).list_models().await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("is `ollama serve` running?"),
+            "{err}"
+        );
+        assert!(err.is_unavailable());
+    }
+}
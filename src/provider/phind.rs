@@ -1,7 +1,9 @@
-use crate::{command::Git, git_commit::GitCommit};
+use crate::ai_prompt::AIPrompt;
 
-use super::AIProvider;
+use super::error::{self, ProviderError};
+use super::{stream, AIProvider, Completion, TextStream};
 use async_trait::async_trait;
+use futures::StreamExt;
 use reqwest::header::{HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
 
@@ -49,93 +51,133 @@ impl PhindProvider {
         }
     }
 
-    async fn create_request(
-        &self,
-        commit_message: Option<&str>,
-        diff_content: &str,
-    ) -> Result<PhindRequest, Box<dyn std::error::Error>> {
-        // Prioritize the diff content for the summary prompt if available
-        let user_input = if let Some(message) = commit_message {
-            format!(
-                    "Please analyze this git commit and provide a summary.\n\nCommit Message:\n{}\n\nDiff Content:\n{}",
-                    message, diff_content
-                )
-        } else if !diff_content.is_empty() {
-            format!(
-            "Please analyze the following staged changes and provide a short, concise title and a detailed summary.\n\nDiff Content:\n{}",
-            diff_content
-        )
-        } else {
-            "No commit message or diff content available.".to_string()
-        };
-
-        Ok(PhindRequest {
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn create_request(&self, prompt: &AIPrompt) -> PhindRequest {
+        let mut message_history = vec![Message {
+            content: prompt.system_prompt.clone(),
+            role: "system".to_string(),
+        }];
+        message_history.extend(prompt.history.iter().map(|message| Message {
+            content: message.content.clone(),
+            role: message.role.name().to_string(),
+        }));
+        message_history.push(Message {
+            content: prompt.user_prompt.clone(),
+            role: "user".to_string(),
+        });
+
+        PhindRequest {
             additional_extension_context: String::new(),
             allow_magic_buttons: true,
             is_vscode_extension: true,
-            message_history: vec![Message {
-                content: user_input.clone(),
-                role: "user".to_string(),
-            }],
+            message_history,
             requested_model: self.model.clone(),
-            user_input,
-        })
+            user_input: prompt.user_prompt.clone(),
+        }
     }
 
-    fn create_headers() -> Result<HeaderMap, Box<dyn std::error::Error>> {
+    fn create_headers() -> HeaderMap {
         let mut headers = HeaderMap::new();
         headers.insert("Content-Type", HeaderValue::from_static("application/json"));
         headers.insert("User-Agent", HeaderValue::from_static(""));
         headers.insert("Accept", HeaderValue::from_static("*/*"));
         headers.insert("Accept-Encoding", HeaderValue::from_static("Identity"));
-        Ok(headers)
+        headers
     }
 
-    async fn get_main_text(response: &str) -> Result<String, Box<dyn std::error::Error>> {
+    fn get_main_text(response: &str) -> String {
         let lines: Vec<&str> = response.split('\n').collect();
         let mut full_text = String::new();
 
         for line in lines {
-            if line.starts_with("data: ") {
-                let obj = line.strip_prefix("data: ").unwrap_or("{}");
-                if let Ok(response) = serde_json::from_str::<PhindResponse>(obj) {
-                    if let Some(choices) = response.choices {
-                        if !choices.is_empty() {
-                            full_text.push_str(&choices[0].delta.content);
-                        }
-                    }
-                }
+            if let Some(content) = Self::parse_line(line) {
+                full_text.push_str(&content);
             }
         }
 
-        Ok(full_text)
+        full_text
     }
-}
 
-#[async_trait]
-impl AIProvider for PhindProvider {
-    async fn explain(&self, git: Git) -> Result<String, Box<dyn std::error::Error>> {
-        let request = match git {
-            Git::Commit(ref commit) => {
-                self.create_request(Some(commit.message.as_str()), commit.diff.as_str())
-                    .await?
-            }
-            Git::Staged(ref staged) => self.create_request(None, staged.diff.as_str()).await?,
-        };
+    fn parse_line(line: &str) -> Option<String> {
+        let obj = line.strip_prefix("data: ")?;
+        let response = serde_json::from_str::<PhindResponse>(obj).ok()?;
+        response
+            .choices?
+            .into_iter()
+            .next()
+            .map(|choice| choice.delta.content)
+    }
 
-        let headers = Self::create_headers()?;
+    /// Phind always streams its response, so `_stream` makes no difference,
+    /// and has no structured output, so `_schema` is left to the prompt.
+    pub fn request(
+        &self,
+        prompt: &AIPrompt,
+        _stream: bool,
+        _schema: Option<&serde_json::Value>,
+    ) -> reqwest::RequestBuilder {
+        self.client
+            .post("https://https.extension.phind.com/agent/")
+            .headers(Self::create_headers())
+            .json(&self.create_request(prompt))
+    }
 
+    async fn send_request(&self, prompt: &AIPrompt) -> Result<reqwest::Response, ProviderError> {
         let response = self
-            .client
-            .post("https://https.extension.phind.com/agent/")
-            .headers(headers)
-            .json(&request)
+            .request(prompt, false, None)
             .send()
-            .await?
+            .await
+            .map_err(|err| ProviderError::from_request("Phind", err))?;
+
+        error::check_response("Phind", response, error::error_message).await
+    }
+
+    /// Phind does not report token usage.
+    pub async fn completion(
+        &self,
+        prompt: &AIPrompt,
+        _schema: Option<&serde_json::Value>,
+    ) -> Result<Completion, ProviderError> {
+        let response = self.send_request(prompt).await?;
+        let response = response
             .text()
-            .await?;
+            .await
+            .map_err(|err| ProviderError::from_request("Phind", err))?;
+
+        let text = Self::get_main_text(&response);
+        if text.is_empty() {
+            return Err(ProviderError::MalformedResponse(
+                "Phind".to_string(),
+                "the response contained no text".to_string(),
+            ));
+        }
+
+        Ok(Completion { text, usage: None })
+    }
+}
+
+#[async_trait]
+impl AIProvider for PhindProvider {
+    async fn complete(&self, prompt: AIPrompt) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.completion(&prompt, None).await?.text)
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: AIPrompt,
+    ) -> Result<TextStream, Box<dyn std::error::Error>> {
+        let response = self.send_request(&prompt).await?;
+
+        let chunks = stream::lines(response).filter_map(|line| async move {
+            match line {
+                Ok(line) => Self::parse_line(&line).map(Ok),
+                Err(err) => Some(Err(ProviderError::from_request("Phind", err).into())),
+            }
+        });
 
-        let res = Self::get_main_text(&response).await?;
-        Ok(res)
+        Ok(Box::pin(chunks))
     }
 }
@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+
+use futures::{future, stream, Stream, StreamExt};
+
+/// Splits a streaming response body into lines, yielding each line as soon as
+/// it is complete. Bytes are buffered until a newline so that multi-byte
+/// characters split across network chunks are decoded correctly.
+pub fn lines(response: reqwest::Response) -> impl Stream<Item = Result<String, reqwest::Error>> {
+    split_lines(response.bytes_stream())
+}
+
+/// Yields the data of every event of a server-sent events stream. The
+/// `data:` lines of an event are joined with newlines; comments and other
+/// fields are skipped.
+pub fn sse_data(response: reqwest::Response) -> impl Stream<Item = Result<String, reqwest::Error>> {
+    events(lines(response))
+}
+
+fn split_lines<B: AsRef<[u8]>, E>(
+    bytes: impl Stream<Item = Result<B, E>>,
+) -> impl Stream<Item = Result<String, E>> {
+    let bytes = Box::pin(bytes);
+
+    stream::unfold(
+        (Some(bytes), Vec::new(), VecDeque::new()),
+        |(mut bytes, mut buffer, mut pending)| async move {
+            loop {
+                if let Some(line) = pending.pop_front() {
+                    return Some((Ok(line), (bytes, buffer, pending)));
+                }
+
+                let body = bytes.as_mut()?;
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.extend_from_slice(chunk.as_ref());
+                        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                            let line: Vec<u8> = buffer.drain(..=pos).collect();
+                            pending.push_back(decode_line(&line));
+                        }
+                    }
+                    Some(Err(err)) => return Some((Err(err), (None, buffer, pending))),
+                    None => {
+                        bytes = None;
+                        if !buffer.is_empty() {
+                            pending.push_back(decode_line(&buffer));
+                            buffer.clear();
+                        }
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// Groups `lines` into events, which end at a blank line or with the stream.
+fn events<E>(
+    lines: impl Stream<Item = Result<String, E>>,
+) -> impl Stream<Item = Result<String, E>> {
+    lines
+        .chain(stream::once(future::ready(Ok(String::new()))))
+        .scan(Vec::new(), |data: &mut Vec<String>, line| {
+            let event = match line {
+                Ok(line) if line.is_empty() => {
+                    (!data.is_empty()).then(|| Ok(std::mem::take(data).join("\n")))
+                }
+                Ok(line) => {
+                    data.extend(data_field(&line).map(String::from));
+                    None
+                }
+                Err(err) => Some(Err(err)),
+            };
+            future::ready(Some(event))
+        })
+        .filter_map(future::ready)
+}
+
+/// The value of a `data` field, without the single space that may follow
+/// its colon.
+fn data_field(line: &str) -> Option<&str> {
+    match line.split_once(':') {
+        Some(("data", value)) => Some(value.strip_prefix(' ').unwrap_or(value)),
+        None if line == "data" => Some(""),
+        _ => None,
+    }
+}
+
+fn decode_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .trim_end_matches(['\r', '\n'])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect<T>(
+        stream: impl Stream<Item = Result<T, &'static str>>,
+    ) -> Vec<Result<T, &'static str>> {
+        stream.collect().await
+    }
+
+    fn chunks(chunks: &[&'static [u8]]) -> impl Stream<Item = Result<&'static [u8], &'static str>> {
+        stream::iter(chunks.to_vec()).map(Ok)
+    }
+
+    #[tokio::test]
+    async fn joins_lines_split_across_chunks() {
+        let lines = split_lines(chunks(&[
+            b"first li",
+            b"ne\r\nsecond\n\nca",
+            b"f\xc3",
+            b"\xa9",
+        ]));
+
+        assert_eq!(
+            collect(lines).await,
+            [
+                Ok("first line".to_string()),
+                Ok("second".to_string()),
+                Ok(String::new()),
+                Ok("café".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_at_an_error() {
+        let bytes = stream::iter([Ok(b"partial".as_slice()), Err("reset"), Ok(b" line\n")]);
+
+        assert_eq!(collect(split_lines(bytes)).await, [Err("reset")]);
+    }
+
+    #[tokio::test]
+    async fn yields_the_data_of_each_event() {
+        let body = chunks(&[
+            b": keep-alive\r\n\r\nevent: content_block_delta\r\ndata: {\"text\"",
+            b": \"hi\"}\r\n\r\nid: 2\ndata:first\ndata\ndata:  indented\n\n",
+            b"retry: 10\n\ndata: [DONE]",
+        ]);
+
+        assert_eq!(
+            collect(events(split_lines(body))).await,
+            [
+                Ok("{\"text\": \"hi\"}".to_string()),
+                Ok("first\n\n indented".to_string()),
+                Ok("[DONE]".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_only_data_fields() {
+        assert_eq!(data_field("data: {}"), Some("{}"));
+        assert_eq!(data_field("data:{}"), Some("{}"));
+        assert_eq!(data_field("data"), Some(""));
+        assert_eq!(data_field(": data: comment"), None);
+        assert_eq!(data_field("database: x"), None);
+        assert_eq!(data_field("event: message_stop"), None);
+    }
+}
@@ -0,0 +1,204 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// A summary broken into the parts that `--output json` reports: a one-line
+/// title, a paragraph describing the change, and the individual changes.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Summary {
+    pub title: String,
+    pub summary: String,
+    pub changes: Vec<String>,
+}
+
+impl Summary {
+    /// The JSON schema providers with structured output are asked to follow.
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": { "type": "string" },
+                "summary": { "type": "string" },
+                "changes": { "type": "array", "items": { "type": "string" } },
+            },
+            "required": ["title", "summary", "changes"],
+            "additionalProperties": false,
+        })
+    }
+
+    /// Parses a response that was asked to follow [`Summary::schema`].
+    /// Providers without structured output may wrap the object in code
+    /// fences or prose, or answer in Markdown after all; those answers are
+    /// split into a title, paragraphs and bullet points instead.
+    pub fn parse(text: &str) -> Self {
+        Self::from_json(text).unwrap_or_else(|| Self::from_markdown(text))
+    }
+
+    fn from_json(text: &str) -> Option<Self> {
+        let start = text.find('{')?;
+        let end = text.rfind('}')?;
+        let value: Value = serde_json::from_str(text.get(start..=end)?).ok()?;
+        let object = value.as_object()?;
+
+        let summary = Summary {
+            title: single_line(&text_field(object.get("title"))),
+            summary: text_field(object.get("summary")),
+            changes: match object.get("changes") {
+                Some(Value::Array(changes)) => changes
+                    .iter()
+                    .map(|change| text_field(Some(change)))
+                    .filter(|change| !change.is_empty())
+                    .collect(),
+                Some(Value::String(changes)) => changes
+                    .lines()
+                    .map(|line| bullet(line.trim()).unwrap_or(line.trim()))
+                    .filter(|change| !change.is_empty())
+                    .map(String::from)
+                    .collect(),
+                _ => Vec::new(),
+            },
+        };
+
+        let is_empty =
+            summary.title.is_empty() && summary.summary.is_empty() && summary.changes.is_empty();
+        (!is_empty).then_some(summary)
+    }
+
+    fn from_markdown(text: &str) -> Self {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("```"))
+            .skip_while(|line| line.is_empty());
+
+        let title = lines.next().map(strip_markup).unwrap_or_default();
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut changes = Vec::new();
+        let mut paragraph = Vec::new();
+
+        for line in lines {
+            if let Some(change) = bullet(line) {
+                changes.push(strip_markup(change));
+            } else if !line.is_empty() && !line.starts_with('#') {
+                paragraph.push(line);
+                continue;
+            }
+
+            if !paragraph.is_empty() {
+                paragraphs.push(paragraph.join(" "));
+                paragraph.clear();
+            }
+        }
+        if !paragraph.is_empty() {
+            paragraphs.push(paragraph.join(" "));
+        }
+
+        Summary {
+            title: single_line(&title),
+            summary: paragraphs.join("\n\n"),
+            changes,
+        }
+    }
+}
+
+/// The text of a field, accepting lists of lines and other values for it.
+fn text_field(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| text_field(Some(value)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(Value::Null) | None => String::new(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// The item of a Markdown list item (`- `, `* `, `+ `, `1. ` or `1) `).
+fn bullet(line: &str) -> Option<&str> {
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some(item.trim());
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    match digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        true => Some(rest[2..].trim()),
+        false => None,
+    }
+}
+
+/// `line` without heading markers, a `Title:` label and emphasis or code
+/// spans wrapping all of it.
+fn strip_markup(line: &str) -> String {
+    let line = line.trim_start_matches('#').trim();
+    let mut line = line.strip_prefix("Title:").unwrap_or(line).trim();
+    while let Some(inner) = ["**", "__", "*", "_", "`"].iter().find_map(|mark| {
+        line.strip_prefix(mark)
+            .and_then(|rest| rest.strip_suffix(mark))
+    }) {
+        line = inner.trim();
+    }
+    line.to_string()
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_well_formed_json() {
+        let summary = Summary::parse(
+            r#"{"title": "Add a cache", "summary": "Caches responses.", "changes": ["Add cache.rs", "Wire it in"]}"#,
+        );
+
+        assert_eq!(summary.title, "Add a cache");
+        assert_eq!(summary.summary, "Caches responses.");
+        assert_eq!(summary.changes, ["Add cache.rs", "Wire it in"]);
+    }
+
+    #[test]
+    fn finds_json_in_fences_and_prose() {
+        let fenced = "```json\n{\"title\": \"Fix\\n the parser\", \"summary\": \"\", \"changes\": \"- one\\n- two\"}\n```";
+        let summary = Summary::parse(fenced);
+        assert_eq!(summary.title, "Fix the parser");
+        assert_eq!(summary.changes, ["one", "two"]);
+
+        let prose = "Here is the summary:\n{\"title\": \"Fix\", \"summary\": \"Done.\", \"changes\": []}\nLet me know!";
+        let summary = Summary::parse(prose);
+        assert_eq!(summary.title, "Fix");
+        assert_eq!(summary.summary, "Done.");
+        assert!(summary.changes.is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_markdown() {
+        let summary = Summary::parse(
+            "## **Add a cache**\n\nResponses are cached\nper commit.\n\n- Add `cache.rs`\n1. Wire it in\n\nThe end.",
+        );
+
+        assert_eq!(summary.title, "Add a cache");
+        assert_eq!(
+            summary.summary,
+            "Responses are cached per commit.\n\nThe end."
+        );
+        assert_eq!(summary.changes, ["Add `cache.rs`", "Wire it in"]);
+    }
+
+    #[test]
+    fn falls_back_when_the_json_is_empty_or_broken() {
+        let summary = Summary::parse("{}\nTitle: Tidy up");
+        assert_eq!(summary.title, "{}");
+
+        let summary = Summary::parse("Use {braces} in {templates");
+        assert_eq!(summary.title, "Use {braces} in {templates");
+        assert!(summary.summary.is_empty());
+    }
+}